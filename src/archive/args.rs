@@ -13,6 +13,18 @@ pub struct Arguments {
     pub archive: Option<PathBuf>,
     #[clap(long, short)]
     pub interactive: bool,
+    #[clap(
+        long,
+        value_name = "FOLDER",
+        help = "path to folder of the series indices"
+    )]
+    pub index_folder: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "FOLDER",
+        help = "path to folder of the exported chapters, their tags are read as well"
+    )]
+    pub export_folder: Option<PathBuf>,
 
     #[clap(long)]
     pub dry_run: bool,
@@ -29,6 +41,8 @@ pub struct Arguments {
 pub struct Config {
     pub version: u8,
     pub path: Option<PathBuf>,
+    pub index_folder: Option<PathBuf>,
+    pub export_folder: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -36,6 +50,8 @@ impl Default for Config {
         Self {
             version: 1,
             path: None,
+            index_folder: None,
+            export_folder: None,
         }
     }
 }
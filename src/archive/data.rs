@@ -5,7 +5,7 @@ use std::{
     ffi::{OsStr, OsString},
     fmt::{Display, Write},
     num::ParseIntError,
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
     time::Duration,
 };
@@ -20,7 +20,10 @@ use thiserror::Error;
 
 use crate::{
     matcher::{mp3_reader::SampleType, start_as_duration},
-    worker::ChapterList,
+    worker::{
+        tagger::{Album, TaggedFile, Title, Track},
+        ChapterList,
+    },
 };
 use common::extensions::{iter::IteratorExt, vec::FindOrPush};
 
@@ -88,6 +91,7 @@ pub fn timelabel_from_peaks<'a, Iter>(
     peaks: Iter,
     sr: u16,
     delay_start: Duration,
+    delay_end: Duration,
     name_pattern: &'a str,
 ) -> impl Iterator<Item = TimeLabel> + 'a
 where
@@ -100,7 +104,7 @@ where
         .map(move |(i, (start, end))| {
             TimeLabel::new::<String>(
                 start + delay_start,
-                end,
+                end + delay_end,
                 Some(name_pattern.replace('#', &i.to_string())),
             )
         })
@@ -108,27 +112,56 @@ where
 #[derive(Debug, Clone)]
 pub struct Archive {
     data: Vec<Series>,
+    /// the label file each source was read from
+    label_files: HashMap<Source, PathBuf>,
 }
 impl Archive {
+    /// the extensions of exported chapters
+    const AUDIO_EXTENSIONS: [&'static str; 2] = ["mp3", "opus"];
+
+    /// reads the label files of the recordings in `path`
+    /// and the tags of the exported chapters in `export_folder`.
     /// will only log warnings, when errors from parsing occure
-    pub fn read(path: impl AsRef<Path>) -> Self {
-        let path = path.as_ref().join("**/*.txt");
-        let pattern = path
-            .to_str()
-            .expect("currently only supporting UTF-8 filenames");
-        let tmp = glob::glob(pattern)
-            .expect("glob pattern failed")
-            .filter_map(|entry| {
-                let entry = entry.expect("couldn't read globbet file");
-                match Source::from_path(&entry) {
-                    Ok(source) => Some((source, TimeLabel::read(&entry).ok()?.into_iter())),
-                    Err(kind) => {
-                        warn!("failed to parse source {entry:?} from filename because {kind:?}");
-                        None
-                    }
-                }
-            });
-        Self::from(tmp)
+    pub fn read(path: impl AsRef<Path>, export_folder: Option<&Path>) -> Self {
+        let path = path.as_ref();
+        let tmp = glob_files(path, "txt").filter_map(|entry| match Source::from_path(&entry) {
+            Ok(source) => {
+                let labels = TimeLabel::read(&entry).ok()?.into_iter();
+                Some((source, entry, labels))
+            }
+            Err(kind) => {
+                warn!("failed to parse source {entry:?} from filename because {kind:?}");
+                None
+            }
+        });
+        let mut archive = Self::from(tmp);
+        let Some(export_folder) = export_folder else {
+            return archive;
+        };
+        archive.add_files(Self::AUDIO_EXTENSIONS.iter().flat_map(|ext| {
+            glob_files(export_folder, ext).filter_map(|entry| {
+                let tag = TaggedFile::from_path(entry.clone(), false)
+                    .map_err(|err| debug!("skipping {entry:?}, because {err}"))
+                    .ok()?;
+                let (Some(series), Some(nr)) = (tag.get::<Album>(), tag.get::<Track>()) else {
+                    debug!("skipping {entry:?}, because it has no album or track");
+                    return None;
+                };
+                let series = series.to_owned();
+                let name = tag.get::<Title>().map(ToOwned::to_owned);
+                Some((entry, series, ChapterNumber::from(nr as usize), name))
+            })
+        }));
+        archive
+    }
+
+    pub fn series(&self) -> impl Iterator<Item = &Series> {
+        self.data.iter()
+    }
+    /// the label file, that `source` was read from
+    #[must_use]
+    pub fn label_file(&self, source: &Source) -> Option<&Path> {
+        self.label_files.get(source).map(PathBuf::as_path)
     }
 
     #[must_use]
@@ -158,11 +191,15 @@ impl Archive {
 
     fn from<InnerIter, Iter>(value: Iter) -> Self
     where
-        Iter: Iterator<Item = (Source, InnerIter)>,
+        Iter: Iterator<Item = (Source, PathBuf, InnerIter)>,
         InnerIter: Iterator<Item = TimeLabel>,
     {
-        let mut archive = Self { data: Vec::new() };
-        for (source, labels) in value {
+        let mut archive = Self {
+            data: Vec::new(),
+            label_files: HashMap::new(),
+        };
+        for (source, label_file, labels) in value {
+            archive.label_files.insert(source.clone(), label_file);
             for label in labels {
                 if label
                     .name()
@@ -198,11 +235,34 @@ impl Archive {
                     .or_insert(1);
             }
         }
-        archive.data.sort_by(|a, b| Ord::cmp(&a.name, &b.name));
-        archive.data.iter_mut().for_each(|s| s.chapters.sort());
+        archive.sort();
         archive
     }
 
+    /// adds exported audio files with the series, number and name of their chapter
+    fn add_files(
+        &mut self,
+        files: impl IntoIterator<Item = (PathBuf, String, ChapterNumber, Option<String>)>,
+    ) {
+        for (path, series_name, ch_nr, chapter_name) in files {
+            let series = self.data.find_or_push_else(
+                || Series::new(series_name.clone()),
+                |it| it.name == series_name,
+            );
+            let chapter = series.chapters.find_or_push_else(
+                || Chapter::new(ch_nr, chapter_name.clone()),
+                |it| it.nr == ch_nr,
+            );
+            chapter.files.push(path);
+        }
+        self.sort();
+    }
+
+    fn sort(&mut self) {
+        self.data.sort_by(|a, b| Ord::cmp(&a.name, &b.name));
+        self.data.iter_mut().for_each(|s| s.chapters.sort());
+    }
+
     #[must_use]
     pub const fn as_display<'a>(
         &'a self,
@@ -338,6 +398,26 @@ impl Series {
             chapters: Vec::new(),
         }
     }
+    #[cfg(test)]
+    pub(crate) fn with_chapters(
+        name: &str,
+        numbers: impl IntoIterator<Item = ChapterNumber>,
+    ) -> Self {
+        let mut series = Self::new(name.to_owned());
+        series.chapters = numbers
+            .into_iter()
+            .map(|nr| Chapter::new(nr, None))
+            .collect();
+        series
+    }
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    #[must_use]
+    pub fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
     #[must_use]
     const fn as_display<'a>(
         &'a self,
@@ -414,6 +494,8 @@ pub struct Chapter {
     nr: ChapterNumber,
     name: Option<String>,
     parts: HashMap<Source, u8>, // source and number of parts in source
+    /// the exported audio files of this chapter
+    files: Vec<PathBuf>,
 }
 
 impl PartialEq for Chapter {
@@ -442,8 +524,30 @@ impl Chapter {
             nr,
             name,
             parts: HashMap::new(),
+            files: Vec::new(),
         }
     }
+    pub const fn nr(&self) -> ChapterNumber {
+        self.nr
+    }
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    /// the sources, that contain this chapter, sorted by their date
+    pub fn sources(&self) -> impl Iterator<Item = &Source> {
+        self.parts.keys().sorted()
+    }
+    /// the exported audio files of this chapter
+    #[must_use]
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+    /// if this chapter exists as an audio file and not just as a label in a recording
+    #[must_use]
+    pub fn is_exported(&self) -> bool {
+        !self.files.is_empty()
+    }
     #[must_use]
     const fn as_display(&self, r_just: Option<(usize, bool)>, l_just: bool) -> ChapterDisplay<'_> {
         ChapterDisplay {
@@ -469,10 +573,24 @@ impl<'a> Display for ChapterDisplay<'a> {
             write!(f, "{name} ")?;
         }
         write!(f, "[{}]", &self.chapter.parts.keys().sorted().join(", "))?;
+        if self.chapter.is_exported() {
+            f.write_str(" (exported)")?;
+        }
         Ok(())
     }
 }
 
+/// all files with `ext` in `path` and its subfolders
+fn glob_files(path: &Path, ext: &str) -> impl Iterator<Item = PathBuf> {
+    let path = path.join(format!("**/*.{ext}"));
+    let pattern = path
+        .to_str()
+        .expect("currently only supporting UTF-8 filenames");
+    glob::glob(pattern)
+        .expect("glob pattern failed")
+        .map(|entry| entry.expect("couldn't read globbet file"))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[must_use]
 pub struct ChapterNumber {
@@ -628,6 +746,14 @@ impl Source {
             .parse()
     }
     #[must_use]
+    pub fn station(&self) -> &str {
+        &self.station
+    }
+    #[must_use]
+    pub const fn date(&self) -> NaiveDate {
+        self.date
+    }
+    #[must_use]
     pub fn to_file_name(&self) -> String {
         format!("{}-{}", self.station, self.date.format(Self::FILE_DATE_FMT))
     }
@@ -721,6 +847,42 @@ mod test {
         }
     }
 
+    mod archive_tests {
+        use super::*;
+
+        #[test]
+        fn files_join_labeled_chapters() {
+            let mut archive = Archive::from(std::iter::once((
+                "station-2023_01_01".parse().unwrap(),
+                PathBuf::from("station-2023_01_01.txt"),
+                vec![TimeLabel::new(
+                    Duration::ZERO,
+                    Duration::from_secs(60),
+                    Some("Serie 1 Anfang"),
+                )]
+                .into_iter(),
+            )));
+            archive.add_files([
+                (
+                    PathBuf::from("Serie/Serie 1 Anfang.mp3"),
+                    "Serie".to_owned(),
+                    ChapterNumber::from(1),
+                    Some("Anfang".to_owned()),
+                ),
+                (
+                    PathBuf::from("Andere/Andere 2.mp3"),
+                    "Andere".to_owned(),
+                    ChapterNumber::from(2),
+                    None,
+                ),
+            ]);
+            assert_eq!(
+                "Andere\n\t2 - [] (exported)\nSerie\n\t1 - Anfang [station - 2023-01-01] (exported)",
+                archive.as_display("\t", false, true, false).to_string()
+            );
+        }
+    }
+
     mod series_tests {
         use super::*;
 
@@ -780,6 +942,13 @@ mod test {
             );
         }
 
+        #[test]
+        fn format_exported() {
+            let mut ch = Chapter::new(ChapterNumber::from(15), None);
+            ch.files.push(PathBuf::from("Serie/Serie 15.mp3"));
+            assert_eq!("15 - [] (exported)", ch.as_display(None, false).to_string());
+        }
+
         #[test]
         fn format_with_name() {
             let ch = Chapter::new(
@@ -0,0 +1,439 @@
+//! finds chapters, that were recorded from several sources, and recommends which copy to keep
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use audacity::data::TimeLabel;
+use itertools::Itertools;
+use log::{debug, info, warn};
+use thiserror::Error;
+
+use super::data::{Archive, ChapterNumber, Source};
+use crate::{
+    matcher::{
+        errors::CliError,
+        mp3_reader::{read_mp3, SampleType},
+    },
+    splitter::{self, frame::Mp3Stream},
+};
+
+/// the length of the windows of a [`Fingerprint`]
+const WINDOW: Duration = Duration::from_millis(100);
+/// how many windows two candidates may be shifted against each other
+const MAX_OFFSET: usize = 300;
+/// candidates with a higher similarity contain the same audio
+const SAME_AUDIO: f32 = 0.9;
+/// candidates this much shorter than the longest one are still complete
+const COMPLETE_TOLERANCE: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Split(#[from] splitter::Error),
+    #[error(transparent)]
+    Decode(#[from] CliError),
+    #[error("couldn't find the recording of {0}")]
+    NoRecording(Source),
+    #[error("couldn't write labels to {0:?}")]
+    WriteLabels(PathBuf),
+}
+
+/// the loudness of a recording over time, which stays the same after reencoding
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fingerprint(Vec<f32>);
+impl Fingerprint {
+    fn from_samples(sample_rate: u16, samples: impl Iterator<Item = SampleType>) -> Self {
+        let window = (sample_rate as usize * WINDOW.as_millis() as usize / 1000).max(1);
+        Self(
+            samples
+                .chunks(window)
+                .into_iter()
+                .map(|chunk| {
+                    let (sum, len) =
+                        chunk.fold((0.0, 0), |(sum, len), it| (sum + it * it, len + 1));
+                    (sum / len as f32).sqrt()
+                })
+                .collect(),
+        )
+    }
+    fn window(time: Duration) -> usize {
+        (time.as_millis() / WINDOW.as_millis()) as usize
+    }
+    /// the part of the fingerprint between `start` and `end`
+    fn slice(&self, start: Duration, end: Duration) -> &[f32] {
+        let end = Self::window(end).min(self.0.len());
+        &self.0[Self::window(start).min(end)..end]
+    }
+
+    /// the best correlation of both fingerprints, when shifted by up to [`MAX_OFFSET`] windows.
+    /// 1 means the same audio, values around 0 unrelated audio
+    #[must_use]
+    pub fn similarity(&self, other: &Self) -> f32 {
+        let min_len = self.0.len().min(other.0.len());
+        (0..=MAX_OFFSET)
+            .flat_map(|offset| [(offset, 0), (0, offset)])
+            .filter_map(|(a, b)| {
+                let a = self.0.get(a..)?;
+                let b = other.0.get(b..)?;
+                let len = a.len().min(b.len());
+                // ignore shifts, that leave to little to compare
+                (len > 1 && len * 2 >= min_len).then(|| correlation(&a[..len], &b[..len]))
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean = |it: &[f32]| it.iter().sum::<f32>() / it.len() as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (a, b) in a.iter().zip(b) {
+        let (a, b) = (a - mean_a, b - mean_b);
+        covariance += a * b;
+        variance_a += a * a;
+        variance_b += b * b;
+    }
+    if variance_a <= f32::EPSILON || variance_b <= f32::EPSILON {
+        return 0.0;
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+/// one recording of a chapter
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub source: Source,
+    /// the start and end of each label of the chapter in the recording of `source`
+    ranges: Vec<(Duration, Duration)>,
+    pub duration: Duration,
+    /// average bitrate in kbit/s
+    pub bitrate: f64,
+    fingerprint: Fingerprint,
+    /// how similar the audio is to the best candidate
+    pub similarity: f32,
+    pub is_complete: bool,
+}
+impl Candidate {
+    /// if this candidate contains the same audio as the best one
+    #[must_use]
+    pub fn is_same(&self) -> bool {
+        self.similarity >= SAME_AUDIO
+    }
+}
+
+/// a chapter with more than one candidate
+#[derive(Debug, Clone)]
+pub struct Duplicate {
+    pub series: String,
+    pub nr: ChapterNumber,
+    pub name: Option<String>,
+    /// the candidate to keep comes first
+    pub candidates: Vec<Candidate>,
+}
+impl Duplicate {
+    fn new(
+        series: String,
+        nr: ChapterNumber,
+        name: Option<String>,
+        mut candidates: Vec<Candidate>,
+    ) -> Self {
+        let longest = candidates
+            .iter()
+            .map(|it| it.duration)
+            .max()
+            .unwrap_or_default();
+        for candidate in &mut candidates {
+            candidate.is_complete = candidate.duration + COMPLETE_TOLERANCE >= longest;
+        }
+        candidates.sort_by(|a, b| {
+            (b.is_complete, b.bitrate, b.duration)
+                .partial_cmp(&(a.is_complete, a.bitrate, a.duration))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        if let Some((best, others)) = candidates.split_first_mut() {
+            best.similarity = 1.0;
+            for candidate in others {
+                candidate.similarity = best.fingerprint.similarity(&candidate.fingerprint);
+            }
+        }
+        Self {
+            series,
+            nr,
+            name,
+            candidates,
+        }
+    }
+    /// the candidates, that contain the same audio as the best one and can be removed
+    pub fn removable(&self) -> impl Iterator<Item = &Candidate> {
+        self.candidates.iter().skip(1).filter(|it| it.is_same())
+    }
+}
+impl Display for Duplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.series, self.nr)?;
+        if let Some(name) = &self.name {
+            write!(f, " {name}")?;
+        }
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            let verdict = match (i, candidate.is_same()) {
+                (0, _) => "keep",
+                (_, true) => "drop",
+                (_, false) => "differs",
+            };
+            let secs = candidate.duration.as_secs();
+            write!(
+                f,
+                "\n\t{verdict:<7} {} {}:{:02}:{:02} {:.0}kbit/s",
+                candidate.source,
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60,
+                candidate.bitrate
+            )?;
+            if i != 0 {
+                write!(f, " {:.0}% similar", candidate.similarity * 100.0)?;
+            }
+            if !candidate.is_complete {
+                f.write_str(" incomplete")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the ranges of the labels of chapter `nr` of `series` in `labels`
+fn chapter_ranges(
+    labels: &[TimeLabel],
+    series: &str,
+    nr: ChapterNumber,
+) -> Vec<(Duration, Duration)> {
+    labels
+        .iter()
+        .filter(|label| {
+            label
+                .name()
+                .and_then(Archive::parse_line)
+                .is_some_and(|(it_series, it_nr, _, _)| it_series == series && it_nr == nr)
+        })
+        .map(|label| (*label.start(), *label.end()))
+        .collect()
+}
+
+/// the recording, that was labeled in `label_file`
+fn recording_path(label_file: &Path) -> Option<PathBuf> {
+    Some(label_file.with_extension("mp3")).filter(|it| it.exists())
+}
+
+/// measures the candidates of all chapters in the recording of `source`.
+/// Each chapter is given by the ranges of its labels
+fn measure(
+    source: &Source,
+    label_file: &Path,
+    chapters: Vec<Vec<(Duration, Duration)>>,
+) -> Result<Vec<Candidate>, Error> {
+    let path = recording_path(label_file).ok_or_else(|| Error::NoRecording(source.clone()))?;
+    debug!("measuring {} chapters in {path:?}", chapters.len());
+    let stream = Mp3Stream::read(&path).map_err(splitter::Error::from)?;
+    let bitrates = chapters
+        .iter()
+        .map(|ranges| {
+            let (bits, duration) = ranges
+                .iter()
+                .flat_map(|(start, end)| stream.frame_range(*start, *end))
+                .map(|i| &stream.frames()[i].header)
+                .fold((0.0, 0.0), |(bits, duration), header| {
+                    let frame_duration = header.duration().as_secs_f64();
+                    (
+                        bits + f64::from(header.bitrate) * frame_duration,
+                        duration + frame_duration,
+                    )
+                });
+            bits / f64::max(duration, f64::EPSILON)
+        })
+        .collect_vec();
+    drop(stream);
+
+    let (sample_rate, samples) = read_mp3(&path)?;
+    let fingerprint = Fingerprint::from_samples(sample_rate, samples);
+    Ok(chapters
+        .into_iter()
+        .zip(bitrates)
+        .map(|(ranges, bitrate)| Candidate {
+            source: source.clone(),
+            duration: ranges.iter().map(|(start, end)| *end - *start).sum(),
+            bitrate,
+            fingerprint: Fingerprint(
+                ranges
+                    .iter()
+                    .flat_map(|(start, end)| fingerprint.slice(*start, *end))
+                    .copied()
+                    .collect(),
+            ),
+            ranges,
+            similarity: 0.0,
+            is_complete: true,
+        })
+        .collect())
+}
+
+/// finds all chapters with copies in more than one source and compares them.
+/// Sources without readable labels or recording are left out with a warning
+pub fn find(archive: &Archive) -> Vec<Duplicate> {
+    let chapters = archive
+        .series()
+        .flat_map(|series| series.chapters().iter().map(move |it| (series, it)))
+        .filter(|(_, chapter)| chapter.sources().count() > 1)
+        .collect_vec();
+
+    // read every recording only once
+    let mut requests = HashMap::<&Source, Vec<usize>>::new();
+    for (i, (_, chapter)) in chapters.iter().enumerate() {
+        for source in chapter.sources() {
+            requests.entry(source).or_default().push(i);
+        }
+    }
+    let mut copies = vec![Vec::new(); chapters.len()];
+    for (source, indices) in requests {
+        let Some(label_file) = archive.label_file(source) else {
+            continue;
+        };
+        let labels = match splitter::read_labels(label_file) {
+            Ok(labels) => labels,
+            Err(err) => {
+                warn!("skipping {source}, because {err}");
+                continue;
+            }
+        };
+        let requested = indices
+            .iter()
+            .map(|&i| {
+                let (series, chapter) = chapters[i];
+                chapter_ranges(&labels, series.name(), chapter.nr())
+            })
+            .collect_vec();
+        match measure(source, label_file, requested) {
+            Ok(measured) => {
+                for (i, copy) in indices.into_iter().zip(measured) {
+                    copies[i].push(copy);
+                }
+            }
+            Err(err) => warn!("skipping {source}, because {err}"),
+        }
+    }
+
+    chapters
+        .into_iter()
+        .zip(copies)
+        .filter(|(_, copies)| copies.len() > 1)
+        .map(|((series, chapter), copies)| {
+            Duplicate::new(
+                series.name().to_owned(),
+                chapter.nr(),
+                chapter.name().map(ToOwned::to_owned),
+                copies,
+            )
+        })
+        .collect()
+}
+
+/// removes the removable copies of `duplicates` from the archive,
+/// by prefixing their labels with '#', so they are skipped when reading the archive
+pub fn keep_best(archive: &Archive, duplicates: &[Duplicate], dry_run: bool) -> Result<(), Error> {
+    let by_source = duplicates
+        .iter()
+        .flat_map(Duplicate::removable)
+        .into_group_map_by(|copy| &copy.source);
+    for (source, copies) in by_source {
+        let Some(label_file) = archive.label_file(source) else {
+            continue;
+        };
+        let mut labels = match splitter::read_labels(label_file) {
+            Ok(labels) => labels,
+            Err(err) => {
+                warn!("skipping {source}, because {err}");
+                continue;
+            }
+        };
+        for label in &mut labels {
+            let range = (*label.start(), *label.end());
+            if copies.iter().any(|copy| copy.ranges.contains(&range)) {
+                info!("dropping {:?} from {source}", label.name());
+                *label = TimeLabel::new(
+                    *label.start(),
+                    *label.end(),
+                    Some(format!("#{}", label.name().unwrap_or_default())),
+                );
+            }
+        }
+        if dry_run {
+            continue;
+        }
+        TimeLabel::write(labels, label_file, false)
+            .map_err(|_| Error::WriteLabels(label_file.to_owned()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(source: &str, secs: u64, bitrate: f64, fingerprint: Vec<f32>) -> Candidate {
+        Candidate {
+            source: source.parse().unwrap(),
+            ranges: Vec::new(),
+            duration: Duration::from_secs(secs),
+            bitrate,
+            fingerprint: Fingerprint(fingerprint),
+            similarity: 0.0,
+            is_complete: true,
+        }
+    }
+
+    #[test]
+    fn similar_fingerprints() {
+        let audio = (0..200).map(|i| (i as f32 * 0.3).sin().abs()).collect_vec();
+        let shifted = Fingerprint(audio[5..].iter().map(|it| it * 0.5).collect());
+        let audio = Fingerprint(audio);
+        assert!(
+            audio.similarity(&shifted) > 0.99,
+            "shifted and quieter is the same"
+        );
+        let other = Fingerprint((0..200).map(|i| (i as f32 * 0.7).cos().abs()).collect());
+        assert!(audio.similarity(&other) < SAME_AUDIO, "different audio");
+    }
+
+    #[test]
+    fn prefers_complete_copies() {
+        let audio = (0..200).map(|i| (i as f32 * 0.3).sin().abs()).collect_vec();
+        let other = (0..100).map(|i| (i as f32 * 0.7).cos().abs()).collect_vec();
+        let duplicate = Duplicate::new(
+            "Gruselkabinett".to_owned(),
+            ChapterNumber::from(6),
+            None,
+            vec![
+                candidate("a-2023_01_01", 1000, 192.0, other),
+                candidate("b-2023_01_02", 2000, 128.0, audio.clone()),
+                candidate("c-2023_01_03", 1995, 160.0, audio),
+            ],
+        );
+        assert_eq!(
+            vec!["c - 2023-01-03", "b - 2023-01-02", "a - 2023-01-01"],
+            duplicate
+                .candidates
+                .iter()
+                .map(|it| it.source.to_string())
+                .collect_vec()
+        );
+        assert_eq!(
+            vec!["b - 2023-01-02"],
+            duplicate
+                .removable()
+                .map(|it| it.source.to_string())
+                .collect_vec()
+        );
+    }
+}
@@ -7,14 +7,26 @@ use thiserror::Error;
 
 use common::args::input::Inputs;
 
-use self::data::Archive;
+use crate::worker::{index::MultiIndex, ChapterList};
+
+use self::{
+    data::Archive,
+    report::{Report, SeriesReport},
+};
 
 pub mod args;
 pub mod data;
+pub mod duplicates;
+pub mod report;
 
 pub fn run(args: &self::args::Arguments) -> Result<(), crate::matcher::errors::CliError> {
     debug!("{args:#?}");
-    let mut holder = Holder::new(args.archive.as_ref().unwrap().clone());
+    let mut holder = Holder::new(
+        args.archive.as_ref().unwrap().clone(),
+        args.export_folder.clone(),
+        args.dry_run,
+    );
+    holder.index_folder = args.index_folder.clone();
 
     if args.interactive {
         holder.work_commands(CommandReader::default());
@@ -30,12 +42,22 @@ pub fn run(args: &self::args::Arguments) -> Result<(), crate::matcher::errors::C
 struct Holder {
     archive: Archive,
     path: PathBuf,
+    /// where the exported chapters are, None to only read the label files
+    export_folder: Option<PathBuf>,
+    dry_run: bool,
+    index_folder: Option<PathBuf>,
+    /// read on first use, because only the report needs it
+    index: Option<MultiIndex<'static>>,
 }
 impl Holder {
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, export_folder: Option<PathBuf>, dry_run: bool) -> Self {
         Self {
-            archive: Archive::read(&path),
+            archive: Archive::read(&path, export_folder.as_deref()),
             path,
+            export_folder,
+            dry_run,
+            index_folder: None,
+            index: None,
         }
     }
     fn work_commands(&mut self, iter: impl Iterator<Item = Option<Command>>) {
@@ -44,7 +66,10 @@ impl Holder {
             match command {
                 None | Some(Command::Exit) => {}
                 Some(Command::Reload { path }) => {
-                    self.archive = Archive::read(path.as_deref().unwrap_or(&self.path));
+                    self.archive = Archive::read(
+                        path.as_deref().unwrap_or(&self.path),
+                        self.export_folder.as_deref(),
+                    );
                 }
                 Some(Command::List {
                     indent,
@@ -57,10 +82,81 @@ impl Holder {
                             .as_display(&indent, false, print_all, print_missing)
                     );
                 }
+                Some(Command::Duplicates { keep_best }) => self.duplicates(keep_best),
+                Some(Command::Report { format, output }) => self.report(format, output),
                 Some(Command::Rename) => println!("comming soon"),
             }
         }
     }
+    fn duplicates(&mut self, keep_best: bool) {
+        let duplicates = duplicates::find(&self.archive);
+        if duplicates.is_empty() {
+            println!("found no chapter with more than one recording");
+            return;
+        }
+        for duplicate in &duplicates {
+            println!("{duplicate}");
+        }
+        if !keep_best {
+            return;
+        }
+        if let Err(err) = duplicates::keep_best(&self.archive, &duplicates, self.dry_run) {
+            log::error!("couldn't remove duplicates, because {err}");
+        }
+        if !self.dry_run {
+            self.archive = Archive::read(&self.path, self.export_folder.as_deref());
+        }
+    }
+    fn index(&mut self) -> &mut MultiIndex<'static> {
+        let folder = self.index_folder.clone();
+        self.index.get_or_insert_with(|| match folder {
+            Some(folder) => tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("couldn't start runtime")
+                .block_on(MultiIndex::new(folder)),
+            None => {
+                warn!("no index folder given, can't know which chapters are missing");
+                MultiIndex::empty()
+            }
+        })
+    }
+    fn report(&mut self, format: report::Format, output: Option<PathBuf>) {
+        let names = self
+            .index()
+            .get_possible()
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        let mut reports = Vec::new();
+        for series in self.archive.series() {
+            let name = std::ffi::OsString::from(series.name());
+            let index = self.index.as_mut().and_then(|it| it.get_known_index(&name));
+            reports.push(SeriesReport::new(
+                series,
+                index.as_ref().map(|it| it as &dyn ChapterList),
+            ));
+        }
+        for name in names {
+            let series = name.to_string_lossy();
+            if self.archive.get_series_by_name(&series).is_some() {
+                continue;
+            }
+            if let Some(index) = self.index.as_mut().and_then(|it| it.get_known_index(&name)) {
+                reports.push(SeriesReport::not_started(series.into_owned(), &index));
+            }
+        }
+        reports.sort_by(|a, b| a.series.cmp(&b.series));
+        let report = Report(reports).as_display(format).to_string();
+        match output {
+            Some(path) => {
+                if let Err(err) = std::fs::write(&path, report + "\n") {
+                    log::error!("couldn't write report to {path:?}, because {err}");
+                }
+            }
+            None => println!("{report}"),
+        }
+    }
 }
 
 #[derive(Debug, Parser)]
@@ -87,6 +183,21 @@ pub enum Command {
         #[clap(name = "print_missing", short = 'm', long)]
         print_missing: bool,
     },
+    /// compares chapters, that were recorded more than once, and recommends which one to keep
+    Duplicates {
+        /// removes the recordings with the same audio as the recommended one from the archive.
+        /// Their labels are commented out with '#', remove it by hand to restore them
+        #[clap(long)]
+        keep_best: bool,
+    },
+    /// compares each series with its index and reports owned, missing and uncertain chapters
+    Report {
+        #[clap(long, value_enum, default_value_t)]
+        format: report::Format,
+        /// writes the report into this file instead of printing it
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
     Rename,
 }
 #[derive(Debug, Error)]
@@ -0,0 +1,273 @@
+//! how complete each series of the archive is, compared with its index
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{Display, Write},
+};
+
+use itertools::Itertools;
+
+use super::data::{ChapterNumber, Series};
+use crate::worker::ChapterList;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Csv,
+    Markdown,
+}
+
+/// the state of one series
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeriesReport {
+    pub series: String,
+    /// the number of chapters in the index, `None` without index
+    pub total: Option<usize>,
+    /// chapters, that are in the archive without '?' or '-'
+    pub owned: usize,
+    /// chapters of the index, that aren't in the archive, with their title
+    pub missing: Vec<(usize, String)>,
+    /// chapters, that are only partially in the archive
+    pub partial: Vec<usize>,
+    /// chapters, that are only in the archive with an uncertain number
+    pub uncertain: Vec<usize>,
+    /// chapters of the archive, that aren't in the index, so they aren't counted
+    pub unknown: Vec<usize>,
+}
+impl SeriesReport {
+    /// compares the chapters of `series` with `index`
+    #[must_use]
+    pub fn new(series: &Series, index: Option<&dyn ChapterList>) -> Self {
+        let mut numbers = BTreeMap::<usize, Vec<ChapterNumber>>::new();
+        for chapter in series.chapters() {
+            numbers
+                .entry(chapter.nr().nr)
+                .or_default()
+                .push(chapter.nr());
+        }
+        let known = index.map(|index| {
+            index
+                .chapter_iter()
+                .map(|(nr, _)| nr.nr)
+                .collect::<BTreeSet<_>>()
+        });
+        let (mut owned, mut partial, mut uncertain, mut unknown) =
+            (0, Vec::new(), Vec::new(), Vec::new());
+        for (&nr, entries) in &numbers {
+            if known.as_ref().is_some_and(|known| !known.contains(&nr)) {
+                unknown.push(nr);
+            } else if entries.iter().any(|it| !it.is_maybe && !it.is_partial) {
+                owned += 1;
+            } else if entries.iter().any(|it| !it.is_maybe) {
+                partial.push(nr);
+            } else {
+                uncertain.push(nr);
+            }
+        }
+        let missing = index.map_or_else(Vec::new, |index| {
+            index
+                .chapter_iter()
+                .filter(|(nr, _)| !numbers.contains_key(&nr.nr))
+                .map(|(nr, title)| (nr.nr, title.into_owned()))
+                .collect()
+        });
+        Self {
+            series: series.name().to_owned(),
+            total: index.map(ChapterList::len),
+            owned,
+            missing,
+            partial,
+            uncertain,
+            unknown,
+        }
+    }
+    /// a series only known from its index
+    #[must_use]
+    pub fn not_started(series: String, index: &dyn ChapterList) -> Self {
+        Self {
+            series,
+            total: Some(index.len()),
+            owned: 0,
+            missing: index
+                .chapter_iter()
+                .map(|(nr, title)| (nr.nr, title.into_owned()))
+                .collect(),
+            partial: Vec::new(),
+            uncertain: Vec::new(),
+            unknown: Vec::new(),
+        }
+    }
+
+    /// the percentage of owned chapters, `None` without index
+    #[must_use]
+    pub fn percentage(&self) -> Option<f64> {
+        self.total
+            .filter(|&total| total > 0)
+            .map(|total| self.owned as f64 * 100.0 / total as f64)
+    }
+
+    fn missing_list(&self) -> String {
+        self.missing
+            .iter()
+            .map(|(nr, title)| format!("{nr} {title}"))
+            .join(", ")
+    }
+    fn percentage_str(&self) -> String {
+        self.percentage()
+            .map_or_else(String::new, |it| format!("{it:.1}%"))
+    }
+}
+
+/// the reports of all series, sorted by their name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report(pub Vec<SeriesReport>);
+impl Report {
+    #[must_use]
+    pub const fn as_display(&self, format: Format) -> ReportDisplay<'_> {
+        ReportDisplay {
+            report: self,
+            format,
+        }
+    }
+}
+
+pub struct ReportDisplay<'a> {
+    report: &'a Report,
+    format: Format,
+}
+impl Display for ReportDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = |it: &SeriesReport| it.total.map_or_else(String::new, |it| it.to_string());
+        match self.format {
+            Format::Text => {
+                for (i, it) in self.report.0.iter().enumerate() {
+                    if i != 0 {
+                        f.write_char('\n')?;
+                    }
+                    write!(f, "{}: {}", it.series, it.owned)?;
+                    if let Some(total) = it.total {
+                        write!(f, "/{total} ({})", it.percentage_str())?;
+                    }
+                    for (name, list) in [
+                        ("missing", it.missing_list()),
+                        ("partial", it.partial.iter().join(", ")),
+                        ("uncertain", it.uncertain.iter().join(", ")),
+                        ("unknown", it.unknown.iter().join(", ")),
+                    ] {
+                        if !list.is_empty() {
+                            write!(f, "\n\t{name}: {list}")?;
+                        }
+                    }
+                }
+            }
+            Format::Csv => {
+                let quote = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+                f.write_str("series,total,owned,percentage,missing,partial,uncertain,unknown")?;
+                for it in &self.report.0 {
+                    write!(
+                        f,
+                        "\n{},{},{},{},{},{},{},{}",
+                        quote(&it.series),
+                        total(it),
+                        it.owned,
+                        it.percentage()
+                            .map_or_else(String::new, |it| format!("{it:.1}")),
+                        quote(&it.missing_list()),
+                        quote(&it.partial.iter().join(" ")),
+                        quote(&it.uncertain.iter().join(" ")),
+                        quote(&it.unknown.iter().join(" ")),
+                    )?;
+                }
+            }
+            Format::Markdown => {
+                let escape = |s: &str| s.replace('|', "\\|");
+                f.write_str(
+                    "| series | total | owned | % | missing | partial | uncertain | unknown |\n",
+                )?;
+                f.write_str("|---|--:|--:|--:|---|---|---|---|")?;
+                for it in &self.report.0 {
+                    write!(
+                        f,
+                        "\n| {} | {} | {} | {} | {} | {} | {} | {} |",
+                        escape(&it.series),
+                        total(it),
+                        it.owned,
+                        it.percentage_str(),
+                        escape(&it.missing_list()),
+                        it.partial.iter().join(", "),
+                        it.uncertain.iter().join(", "),
+                        it.unknown.iter().join(", "),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::index::Index;
+
+    fn report() -> Report {
+        let index = Index::from_toml_str(
+            r#"chapters.main = ["Eins", "Zwei", "Drei", "Vier", "F|nf"]"#,
+            "Serie",
+        )
+        .unwrap();
+        let series = Series::with_chapters(
+            "Serie",
+            ["1", "2-", "3?", "3?-"].map(|it| it.parse().unwrap()),
+        );
+        Report(vec![SeriesReport::new(&series, Some(&&index))])
+    }
+
+    #[test]
+    fn counts_chapters() {
+        let report = report();
+        assert_eq!(
+            SeriesReport {
+                series: "Serie".to_owned(),
+                total: Some(5),
+                owned: 1,
+                missing: vec![(4, "Vier".to_owned()), (5, "F|nf".to_owned())],
+                partial: vec![2],
+                uncertain: vec![3],
+                unknown: Vec::new(),
+            },
+            *report.0.first().unwrap()
+        );
+        assert_eq!(
+            "Serie: 1/5 (20.0%)\n\tmissing: 4 Vier, 5 F|nf\n\tpartial: 2\n\tuncertain: 3",
+            report.as_display(Format::Text).to_string()
+        );
+    }
+
+    #[test]
+    fn export_formats() {
+        let report = report();
+        assert_eq!(
+            "series,total,owned,percentage,missing,partial,uncertain,unknown\n\"Serie\",5,1,20.0,\"4 Vier, 5 F|nf\",\"2\",\"3\",\"\"",
+            report.as_display(Format::Csv).to_string()
+        );
+        assert_eq!(
+            "| series | total | owned | % | missing | partial | uncertain | unknown |\n|---|--:|--:|--:|---|---|---|---|\n| Serie | 5 | 1 | 20.0% | 4 Vier, 5 F\\|nf | 2 | 3 |  |",
+            report.as_display(Format::Markdown).to_string()
+        );
+    }
+
+    #[test]
+    fn ignores_chapters_beyond_index() {
+        let index = Index::from_toml_str(r#"chapters.main = ["Eins", "Zwei"]"#, "Serie").unwrap();
+        let series =
+            Series::with_chapters("Serie", ["1", "2", "3", "7?"].map(|it| it.parse().unwrap()));
+        let report = SeriesReport::new(&series, Some(&&index));
+        assert_eq!(2, report.owned);
+        assert_eq!(vec![3, 7], report.unknown);
+        assert!(
+            report.percentage().is_some_and(|it| it <= 100.0),
+            "more than everything owned"
+        );
+    }
+}
@@ -30,6 +30,12 @@ fn main() {
             .clone()
             .expect("need at least one path, either in path or in config")
     });
+    if args.index_folder.is_none() {
+        args.index_folder = config.index_folder;
+    }
+    if args.export_folder.is_none() {
+        args.export_folder = config.export_folder;
+    }
     audio_matcher::archive::run(&args).unwrap_or_else(|e| {
         log::error!("Program error :'{e}'");
         std::process::exit(1);
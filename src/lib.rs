@@ -1,6 +1,7 @@
 pub mod archive;
 pub mod args;
 pub mod matcher;
+pub mod splitter;
 pub mod worker;
 
 pub const APP_NAME: &str = "audio-matcher"; // on change remember to change value in audacity
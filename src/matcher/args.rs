@@ -7,23 +7,36 @@ use common::args::{debug::OutputLevel, input::Inputs};
 #[derive(Debug, Parser, Clone)]
 #[clap(version = env!("CARGO_PKG_VERSION"))]
 pub struct Arguments {
-    #[clap(value_name = "FILE", help = "file in which samples are searched")]
+    #[clap(
+        value_name = "FILE",
+        help = "file in which samples are searched, or folder of such files"
+    )]
     pub within: Vec<PathBuf>,
 
-    #[clap(long, value_name = "FILE", help = "snippet to be found in file")]
-    pub snippet: PathBuf,
+    #[clap(
+        long,
+        value_name = "FILE",
+        required_unless_present = "profiles",
+        help = "snippet to be found in file, overrides the profiles"
+    )]
+    pub snippet: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "toml file with matcher profiles for each station"
+    )]
+    pub profiles: Option<PathBuf>,
 
     #[clap(
         short,
         long,
-        default_value_t = 13.0 as crate::matcher::mp3_reader::SampleType,
-        help = "minimum prominence of the peaks"
+        help = "minimum prominence of the peaks, overrides the profiles [default: 13]"
     )]
-    pub prominence: crate::matcher::mp3_reader::SampleType,
+    prominence: Option<crate::matcher::mp3_reader::SampleType>,
     #[clap(
         long,
         value_name = "SECONDS",
-        help = "minimum distance between matches in seconds"
+        help = "minimum distance between matches in seconds, overrides the profiles [default: 8m]"
     )]
     #[arg(value_parser = parse_duration)]
     distance: Option<Duration>,
@@ -70,8 +83,35 @@ impl Arguments {
     pub fn chunk_size(&self) -> Duration {
         self.chunk_size.unwrap_or(Duration::from_secs(60))
     }
+    /// the prominence given on the command line
     #[must_use]
-    pub fn distance(&self) -> Duration {
-        self.distance.unwrap_or(Duration::from_secs(8 * 60))
+    pub const fn prominence(&self) -> Option<crate::matcher::mp3_reader::SampleType> {
+        self.prominence
+    }
+    /// the distance given on the command line
+    #[must_use]
+    pub const fn distance(&self) -> Option<Duration> {
+        self.distance
+    }
+    /// all files of `within`, where folders are replaced by the mp3 files they contain
+    pub fn within_files(&self) -> Result<Vec<PathBuf>, std::io::Error> {
+        let mut files = Vec::new();
+        for path in &self.within {
+            if path.is_dir() {
+                let mut content = std::fs::read_dir(path)?
+                    .map(|entry| entry.map(|it| it.path()))
+                    .filter(|path| {
+                        path.as_ref().map_or(true, |path| {
+                            path.extension().is_some_and(|ext| ext == "mp3")
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                content.sort();
+                files.append(&mut content);
+            } else {
+                files.push(path.clone());
+            }
+        }
+        Ok(files)
     }
 }
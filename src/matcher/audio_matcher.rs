@@ -1,5 +1,5 @@
 use crate::{
-    matcher::{args::Arguments, mp3_reader::SampleType, start_as_duration},
+    matcher::{args::Arguments, mp3_reader::SampleType, profile::Profile, start_as_duration},
     offset_range,
 };
 use common::extensions::iter::CloneIteratorExt;
@@ -35,13 +35,13 @@ struct PeakConfig {
 }
 impl Config {
     #[must_use]
-    pub fn from_args(args: &Arguments, s_duration: Duration) -> Self {
+    pub fn from_args(args: &Arguments, profile: &Profile, s_duration: Duration) -> Self {
         Self {
             chunk_size: args.chunk_size(),
             overlap_length: s_duration,
             peak_config: PeakConfig {
-                distance: args.distance(),
-                prominence: args.prominence / 100.0,
+                distance: profile.distance,
+                prominence: profile.prominence / 100.0,
             },
             arrow: if args.fancy_bar {
                 Box::<Fancy>::default()
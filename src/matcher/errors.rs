@@ -18,6 +18,12 @@ pub enum CliError {
 
     #[error("no valid mp3 data in {0}")]
     NoMp3(PathWrap),
+
+    #[error("no snippet given for {0}, neither in profile nor as argument")]
+    NoSnippet(PathWrap),
+
+    #[error("couldn't parse profiles in {0}, because {1}")]
+    InvalidProfile(PathWrap, #[source] toml::de::Error),
     // #[error("data store disconnected")]
     // Disconnect(#[from] io::Error),
     // #[error("invalid header (expected {expected:?}, found {found:?})")]
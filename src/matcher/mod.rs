@@ -3,13 +3,18 @@ pub mod args;
 pub mod audio_matcher;
 pub mod errors;
 pub mod mp3_reader;
+pub mod profile;
 
-use std::time::Duration;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    time::Duration,
+};
 
-use crate::archive::data::timelabel_from_peaks;
+use crate::{archive::data::timelabel_from_peaks, splitter::recording::Recording};
 use audacity::data::TimeLabel;
 use common::extensions::{duration::Ext, iter::IteratorExt};
 use errors::CliError;
+use itertools::Itertools;
 use log::{debug, info, log, trace};
 
 use mp3_reader::SampleType;
@@ -17,29 +22,32 @@ use mp3_reader::SampleType;
 pub fn run(args: &args::Arguments) -> Result<(), CliError> {
     debug!("{args:#?}");
 
+    let within = Recording::group(args.within_files().map_err(|_| {
+        CliError::NoFile(args.within.iter().map(|it| it.display()).join(", ").into())
+    })?);
     if args.out_file.out_file.is_some() {
         assert_eq!(
             1,
-            args.within.len(),
+            within.len(),
             "providet outfile only compatible with one main file"
         );
     }
-
-    trace!("collecting snippet data");
-    let (sr, s_samples) = mp3_reader::read_mp3(&args.snippet)?;
-    let s_duration = mp3_reader::mp3_duration(&args.snippet, false)?;
-
-    let sample_data = s_samples.collect::<Box<[SampleType]>>();
-    trace!("preparing algo");
-    let algo = audio_matcher::LibConvolve::new(sample_data);
-    let level = if args.within.len() == 1 {
+    let profiles = args
+        .profiles
+        .as_ref()
+        .map_or_else(|| Ok(profile::Profiles::default()), profile::Profiles::read)?;
+    // the prepared snippets, so each snippet is only read once
+    let mut snippets = HashMap::new();
+
+    let level = if within.len() == 1 {
         // log number of iterations only if more than one file is processed
         log::Level::Trace
     } else {
         log::Level::Info
     };
 
-    for main_file in &args.within {
+    for recording in &within {
+        let main_file = recording.path();
         let out_path = args
             .out_file
             .out_file
@@ -65,25 +73,40 @@ pub fn run(args: &args::Arguments) -> Result<(), CliError> {
             out_path
         };
 
+        let profile = profiles.resolve(main_file, args)?;
+        debug!("using {profile:?} for '{}'", main_file.display());
+        let (sr, s_duration, algo) = &*match snippets.entry(profile.snippet.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(prepare_snippet(&profile.snippet)?),
+        };
+        let sr = *sr;
+
         // TODO only fail this loop iteration
         log!(level, "preparing data of '{}'", main_file.display());
 
-        let (m_sr, m_samples) = mp3_reader::read_mp3(&main_file)?;
-        if sr != m_sr {
-            return Err(errors::CliError::SampleRateMismatch(sr, m_sr));
-        }
+        // the parts of a split recording are searched as one, so matches can cross their boundary
+        let mut m_samples: Box<dyn Iterator<Item = SampleType> + Send + Sync> =
+            Box::new(std::iter::empty());
+        let mut m_duration = Duration::ZERO;
+        for part in recording.parts() {
+            let (m_sr, part_samples) = mp3_reader::read_mp3(part)?;
+            if sr != m_sr {
+                return Err(errors::CliError::SampleRateMismatch(sr, m_sr));
+            }
+            m_samples = Box::new(m_samples.chain(part_samples));
 
-        trace!("collecting main duration");
-        let m_duration = mp3_reader::mp3_duration(main_file, false)?;
+            trace!("collecting duration of '{}'", part.display());
+            m_duration += mp3_reader::mp3_duration(part, false)?;
+        }
         let samples = (m_duration.as_secs_f64() * sr as f64) as usize;
         trace!("duration is {m_duration:?} with sr {sr} impling #{samples} samples");
         trace!("calculation chunks");
         let peaks = audio_matcher::calc_chunks(
             sr,
             m_samples.with_size(samples),
-            &algo,
+            algo,
             true,
-            audio_matcher::Config::from_args(args, s_duration),
+            audio_matcher::Config::from_args(args, &profile, *s_duration),
         );
 
         print_offsets(&peaks, sr);
@@ -92,7 +115,13 @@ pub fn run(args: &args::Arguments) -> Result<(), CliError> {
         if let Some(out_path) = out_path {
             trace!("writing result to '{}'", out_path.display());
             TimeLabel::write(
-                timelabel_from_peaks(peaks.iter(), sr, Duration::from_secs(7), "Segment #"),
+                timelabel_from_peaks(
+                    peaks.iter(),
+                    sr,
+                    profile.start_offset,
+                    profile.end_offset,
+                    "Segment #",
+                ),
                 &out_path,
                 args.dry_run,
             )
@@ -103,6 +132,18 @@ pub fn run(args: &args::Arguments) -> Result<(), CliError> {
     Ok(())
 }
 
+fn prepare_snippet(
+    snippet: &std::path::Path,
+) -> Result<(u16, Duration, audio_matcher::LibConvolve), CliError> {
+    trace!("collecting snippet data of '{}'", snippet.display());
+    let (sr, s_samples) = mp3_reader::read_mp3(snippet)?;
+    let s_duration = mp3_reader::mp3_duration(snippet, false)?;
+
+    let sample_data = s_samples.collect::<Box<[SampleType]>>();
+    trace!("preparing algo");
+    Ok((sr, s_duration, audio_matcher::LibConvolve::new(sample_data)))
+}
+
 fn auto_out_file(path: impl AsRef<std::path::Path>) -> std::path::PathBuf {
     path.as_ref().with_extension("txt")
 }
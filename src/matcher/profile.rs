@@ -0,0 +1,263 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    archive::data::Source,
+    matcher::{args::Arguments, errors::CliError, mp3_reader::SampleType},
+};
+
+/// the resolved settings used to match one recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub snippet: PathBuf,
+    pub prominence: SampleType,
+    pub distance: Duration,
+    /// offset added to the start of each label, relative to the found snippet
+    pub start_offset: Duration,
+    /// offset added to the end of each label, relative to the next found snippet
+    pub end_offset: Duration,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct RawProfile {
+    snippet: Option<PathBuf>,
+    prominence: Option<SampleType>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    distance: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    start_offset: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    end_offset: Option<Duration>,
+}
+impl RawProfile {
+    /// fills all `None` values of `self` with the values of `other`
+    fn or(&self, other: &Self) -> Self {
+        Self {
+            snippet: self.snippet.clone().or_else(|| other.snippet.clone()),
+            prominence: self.prominence.or(other.prominence),
+            distance: self.distance.or(other.distance),
+            start_offset: self.start_offset.or(other.start_offset),
+            end_offset: self.end_offset.or(other.end_offset),
+        }
+    }
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SecondsOrStr {
+        Seconds(u64),
+        Str(String),
+    }
+    Ok(match Option::<SecondsOrStr>::deserialize(deserializer)? {
+        None => None,
+        Some(SecondsOrStr::Seconds(seconds)) => Some(Duration::from_secs(seconds)),
+        Some(SecondsOrStr::Str(s)) => {
+            Some(crate::args::parse_duration(&s).map_err(serde::de::Error::custom)?)
+        }
+    })
+}
+
+/// a table of [`Profile`]s keyed by the station of a [`Source`]
+///
+/// # Example
+/// ```toml
+/// [default]
+/// snippet = "Interlude.mp3"
+///
+/// [station."89.0rtl"]
+/// prominence = 15.0
+/// distance = "8m"
+/// start_offset = "7s"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profiles {
+    #[serde(default)]
+    default: RawProfile,
+    #[serde(default)]
+    station: HashMap<String, RawProfile>,
+    /// relative snippet paths are resolved against this folder
+    #[serde(skip)]
+    base: Option<PathBuf>,
+}
+
+impl Profiles {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, CliError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|_| CliError::NoFile(path.into()))?;
+        let mut profiles = Self::from_toml_str(&content)
+            .map_err(|err| CliError::InvalidProfile(path.into(), err))?;
+        profiles.base = path.parent().map(Path::to_path_buf);
+        Ok(profiles)
+    }
+    pub fn from_toml_str(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// finds the profile for the station encoded in the filename of `path`.
+    /// Values given in `args` take precedence, missing values are taken from the default profile
+    pub fn resolve(&self, path: impl AsRef<Path>, args: &Arguments) -> Result<Profile, CliError> {
+        let path = path.as_ref();
+        let station = match Source::from_path(path) {
+            Ok(source) => {
+                let profile = self.station.get(source.station());
+                if profile.is_none() {
+                    debug!("no profile for station {:?}", source.station());
+                }
+                profile
+            }
+            Err(kind) => {
+                warn!("couldn't read station from {path:?} because {kind}, using default profile");
+                None
+            }
+        };
+        let raw = station.map_or_else(|| self.default.clone(), |it| it.or(&self.default));
+
+        let snippet = args
+            .snippet
+            .clone()
+            .or_else(|| {
+                raw.snippet.map(|snippet| match &self.base {
+                    Some(base) if snippet.is_relative() => base.join(snippet),
+                    _ => snippet,
+                })
+            })
+            .ok_or_else(|| CliError::NoSnippet(path.into()))?;
+        Ok(Profile {
+            snippet,
+            prominence: args
+                .prominence()
+                .or(raw.prominence)
+                .unwrap_or(Profile::DEFAULT_PROMINENCE),
+            distance: args
+                .distance()
+                .or(raw.distance)
+                .unwrap_or(Profile::DEFAULT_DISTANCE),
+            start_offset: raw.start_offset.unwrap_or(Profile::DEFAULT_START_OFFSET),
+            end_offset: raw.end_offset.unwrap_or(Duration::ZERO),
+        })
+    }
+}
+impl Profile {
+    pub const DEFAULT_START_OFFSET: Duration = Duration::from_secs(7);
+    pub const DEFAULT_PROMINENCE: SampleType = 13.0;
+    pub const DEFAULT_DISTANCE: Duration = Duration::from_secs(8 * 60);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn args() -> Arguments {
+        Arguments::parse_from(["", "--snippet", "cli.mp3", "-p", "10"])
+    }
+    /// only profiles, nothing given on the command line
+    fn profile_args() -> Arguments {
+        Arguments::parse_from(["", "--profiles", "profiles.toml"])
+    }
+
+    const PROFILES: &str = r#"
+        [default]
+        snippet = "/default.mp3"
+        distance = 300
+
+        [station."89.0rtl"]
+        snippet = "/rtl.mp3"
+        prominence = 15.0
+        start_offset = "5s"
+
+        [station.other]
+        distance = "10m"
+    "#;
+
+    #[test]
+    fn station_profile() {
+        let profiles = Profiles::from_toml_str(PROFILES).unwrap();
+        assert_eq!(
+            Profile {
+                snippet: "/rtl.mp3".into(),
+                prominence: 15.0,
+                distance: Duration::from_secs(300),
+                start_offset: Duration::from_secs(5),
+                end_offset: Duration::ZERO,
+            },
+            profiles
+                .resolve("/89.0rtl-2023_06_17.mp3", &profile_args())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn args_override_profiles() {
+        let profiles = Profiles::from_toml_str(PROFILES).unwrap();
+        let rtl = profiles
+            .resolve("/89.0rtl-2023_06_17.mp3", &args())
+            .unwrap();
+        assert_eq!(PathBuf::from("cli.mp3"), rtl.snippet);
+        assert!(
+            (rtl.prominence - 10.0).abs() < SampleType::EPSILON,
+            "prominence of the profile used"
+        );
+        assert_eq!(Duration::from_secs(300), rtl.distance);
+    }
+
+    #[test]
+    fn fallback_to_default() {
+        let profiles = Profiles::from_toml_str(PROFILES).unwrap();
+        let other = profiles
+            .resolve("/other-2023_06_17.mp3", &profile_args())
+            .unwrap();
+        assert_eq!(PathBuf::from("/default.mp3"), other.snippet);
+        assert_eq!(Duration::from_secs(600), other.distance);
+
+        let unknown = profiles
+            .resolve("/unknown-2023_06_17.mp3", &profile_args())
+            .unwrap();
+        assert_eq!(PathBuf::from("/default.mp3"), unknown.snippet);
+        assert_eq!(Duration::from_secs(300), unknown.distance);
+
+        let invalid = profiles.resolve("/no_source.mp3", &profile_args()).unwrap();
+        assert_eq!(PathBuf::from("/default.mp3"), invalid.snippet);
+    }
+
+    #[test]
+    fn fallback_to_args() {
+        let profiles = Profiles::default();
+        assert_eq!(
+            Profile {
+                snippet: "cli.mp3".into(),
+                prominence: 10.0,
+                distance: Duration::from_secs(8 * 60),
+                start_offset: Profile::DEFAULT_START_OFFSET,
+                end_offset: Duration::ZERO,
+            },
+            profiles
+                .resolve("/89.0rtl-2023_06_17.mp3", &args())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn relative_snippet() {
+        let mut profiles = Profiles::from_toml_str("default.snippet = \"snippet.mp3\"").unwrap();
+        profiles.base = Some("/profiles".into());
+        assert_eq!(
+            PathBuf::from("/profiles/snippet.mp3"),
+            profiles
+                .resolve("/a-2023_06_17.mp3", &profile_args())
+                .unwrap()
+                .snippet
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use common::args::debug::OutputLevel;
+
+use super::Format;
+
+#[derive(Debug, Parser, Clone)]
+#[clap(version = env!("CARGO_PKG_VERSION"))]
+pub struct Arguments {
+    #[clap(value_name = "FILE", help = "path to the recording")]
+    pub recording: PathBuf,
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "path to the label file, defaults to the recording with .txt"
+    )]
+    pub labels: Option<PathBuf>,
+    #[clap(
+        long,
+        short,
+        value_name = "FOLDER",
+        help = "folder for the exported files, defaults to the folder of the recording"
+    )]
+    pub out_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = Format::Mp3,
+        help = "format of the exported files"
+    )]
+    pub format: Format,
+    #[clap(long, help = "genre written to the exported files")]
+    pub genre: Option<String>,
+
+    #[clap(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub output_level: OutputLevel,
+}
+
+impl Arguments {
+    #[must_use]
+    pub fn label_path(&self) -> PathBuf {
+        self.labels
+            .clone()
+            .unwrap_or_else(|| self.recording.with_extension("txt"))
+    }
+    #[must_use]
+    pub fn out_dir(&self) -> PathBuf {
+        self.out_dir.clone().unwrap_or_else(|| {
+            self.recording
+                .parent()
+                .expect("path without parent")
+                .to_path_buf()
+        })
+    }
+}
@@ -0,0 +1,503 @@
+use std::{ops::Range, path::Path, time::Duration};
+
+use log::{debug, trace, warn};
+use thiserror::Error;
+
+use super::xing::{read_gapless, Gapless};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("couldn't read {0:?}, because {1}")]
+    IO(std::path::PathBuf, #[source] std::io::Error),
+    #[error("no valid mp3 frame found")]
+    NoFrames,
+    #[error("sample rate changed from {0} to {1} at byte {2}")]
+    SampleRateChanged(u32, u32, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelMode {
+    Stereo,
+    JointStereo,
+    DualChannel,
+    Mono,
+}
+
+/// the header of a MPEG-1/2/2.5 Layer III frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub version: Version,
+    pub has_crc: bool,
+    /// bitrate in kbit/s
+    pub bitrate: u32,
+    pub sample_rate: u32,
+    pub padding: bool,
+    pub channel_mode: ChannelMode,
+}
+impl FrameHeader {
+    pub const SIZE: usize = 4;
+    const BITRATES_V1: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const BITRATES_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+    /// parses the header at the start of `data`, only Layer III with a known bitrate is accepted
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let &[b0, b1, b2, b3, ..] = data else {
+            return None;
+        };
+        if b0 != 0xFF || b1 & 0xE0 != 0xE0 {
+            return None;
+        }
+        let version = match (b1 >> 3) & 0b11 {
+            0b00 => Version::Mpeg25,
+            0b10 => Version::Mpeg2,
+            0b11 => Version::Mpeg1,
+            _ => return None,
+        };
+        if (b1 >> 1) & 0b11 != 0b01 {
+            return None; // not Layer III
+        }
+        let bitrate_index = (b2 >> 4) as usize;
+        let bitrate = *match version {
+            Version::Mpeg1 => Self::BITRATES_V1.get(bitrate_index),
+            Version::Mpeg2 | Version::Mpeg25 => Self::BITRATES_V2.get(bitrate_index),
+        }
+        .filter(|&&it| it != 0)?; // free format isn't supported
+
+        let base_rate = match (b2 >> 2) & 0b11 {
+            0b00 => 44100,
+            0b01 => 48000,
+            0b10 => 32000,
+            _ => return None,
+        };
+        let sample_rate = match version {
+            Version::Mpeg1 => base_rate,
+            Version::Mpeg2 => base_rate / 2,
+            Version::Mpeg25 => base_rate / 4,
+        };
+        let channel_mode = match b3 >> 6 {
+            0b00 => ChannelMode::Stereo,
+            0b01 => ChannelMode::JointStereo,
+            0b10 => ChannelMode::DualChannel,
+            _ => ChannelMode::Mono,
+        };
+        Some(Self {
+            version,
+            has_crc: b1 & 1 == 0,
+            bitrate,
+            sample_rate,
+            padding: (b2 >> 1) & 1 == 1,
+            channel_mode,
+        })
+    }
+
+    #[must_use]
+    pub const fn samples_per_frame(&self) -> usize {
+        match self.version {
+            Version::Mpeg1 => 1152,
+            Version::Mpeg2 | Version::Mpeg25 => 576,
+        }
+    }
+    /// the length of the whole frame in bytes, including the header
+    #[must_use]
+    pub const fn frame_len(&self) -> usize {
+        let factor = match self.version {
+            Version::Mpeg1 => 144,
+            Version::Mpeg2 | Version::Mpeg25 => 72,
+        };
+        (factor * self.bitrate * 1000 / self.sample_rate) as usize + self.padding as usize
+    }
+    #[must_use]
+    pub const fn side_info_len(&self) -> usize {
+        match (self.version, self.channel_mode) {
+            (Version::Mpeg1, ChannelMode::Mono) => 17,
+            (Version::Mpeg1, _) => 32,
+            (_, ChannelMode::Mono) => 9,
+            (_, _) => 17,
+        }
+    }
+    /// offset of the side info from the frame start
+    #[must_use]
+    pub const fn side_info_offset(&self) -> usize {
+        Self::SIZE + if self.has_crc { 2 } else { 0 }
+    }
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples_per_frame() as f64 / self.sample_rate as f64)
+    }
+    /// the number of bytes available for main data in this frame
+    #[must_use]
+    pub const fn main_data_capacity(&self) -> usize {
+        self.frame_len() - self.side_info_offset() - self.side_info_len()
+    }
+    /// the biggest value `main_data_begin` can hold
+    #[must_use]
+    pub const fn max_main_data_begin(&self) -> usize {
+        match self.version {
+            Version::Mpeg1 => 511,
+            Version::Mpeg2 | Version::Mpeg25 => 255,
+        }
+    }
+    /// checks if frames with `self` and `other` can be part of the same stream
+    #[must_use]
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.sample_rate == other.sample_rate
+            && (self.channel_mode == ChannelMode::Mono) == (other.channel_mode == ChannelMode::Mono)
+    }
+
+    /// returns `bytes` with the bitrate index `index`, no padding and no crc
+    #[must_use]
+    pub const fn with_bitrate_index(bytes: [u8; 4], index: u8) -> [u8; 4] {
+        let [b0, b1, b2, b3] = bytes;
+        [b0, b1 | 1, (index << 4) | (b2 & 0b0000_1100), b3]
+    }
+    /// all bitrate indices, starting with the smallest bitrate
+    pub fn bitrate_indices() -> impl Iterator<Item = u8> {
+        1..15
+    }
+
+    /// reads the `main_data_begin` field of `side_info`
+    #[must_use]
+    pub fn main_data_begin(&self, side_info: &[u8]) -> usize {
+        match self.version {
+            Version::Mpeg1 => read_bits(side_info, 0, 9) as usize,
+            Version::Mpeg2 | Version::Mpeg25 => read_bits(side_info, 0, 8) as usize,
+        }
+    }
+    /// updates the `main_data_begin` field of `side_info`
+    pub fn set_main_data_begin(&self, side_info: &mut [u8], value: usize) {
+        assert!(
+            value <= self.max_main_data_begin(),
+            "main_data_begin {value} to big"
+        );
+        match (self.version, side_info) {
+            (Version::Mpeg1, [b0, b1, ..]) => {
+                *b0 = (value >> 1) as u8;
+                *b1 = (*b1 & 0x7F) | (((value & 1) as u8) << 7);
+            }
+            (Version::Mpeg2 | Version::Mpeg25, [b0, ..]) => *b0 = value as u8,
+            (_, _) => unreachable!("side info to short"),
+        }
+    }
+    /// the length of the main data described by `side_info`
+    #[must_use]
+    pub fn main_data_len(&self, side_info: &[u8]) -> usize {
+        let mono = self.channel_mode == ChannelMode::Mono;
+        let channels = if mono { 1 } else { 2 };
+        // offsets and sizes from ISO/IEC 11172-3 and 13818-3
+        let bits: u32 = match self.version {
+            Version::Mpeg1 => {
+                let start = 9 + if mono { 5 } else { 3 } + 4 * channels;
+                (0..2 * channels)
+                    .map(|i| read_bits(side_info, start + i * 59, 12))
+                    .sum()
+            }
+            Version::Mpeg2 | Version::Mpeg25 => {
+                let start = 8 + if mono { 1 } else { 2 };
+                (0..channels)
+                    .map(|i| read_bits(side_info, start + i * 63, 12))
+                    .sum()
+            }
+        };
+        (bits as usize + 7) / 8
+    }
+}
+
+/// reads `len` bits starting at bit `offset` of `data`, msb first
+fn read_bits(data: &[u8], offset: usize, len: usize) -> u32 {
+    (offset..offset + len).fold(0, |acc, bit| {
+        (acc << 1) | u32::from((data[bit / 8] >> (7 - bit % 8)) & 1)
+    })
+}
+
+/// a frame found in a [`Mp3Stream`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub header: FrameHeader,
+    /// position of the frame in the data of the stream
+    pub range: Range<usize>,
+}
+impl Frame {
+    /// returns the bytes of this frame
+    #[must_use]
+    pub fn data<'d>(&self, stream_data: &'d [u8]) -> &'d [u8] {
+        &stream_data[self.range.clone()]
+    }
+    /// the header bytes of this frame
+    #[must_use]
+    pub fn header_bytes(&self, stream_data: &[u8]) -> [u8; 4] {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.data(stream_data)[..FrameHeader::SIZE]);
+        bytes
+    }
+    /// the side info of this frame
+    #[must_use]
+    pub fn side_info<'d>(&self, stream_data: &'d [u8]) -> &'d [u8] {
+        let start = self.range.start + self.header.side_info_offset();
+        &stream_data[start..start + self.header.side_info_len()]
+    }
+    /// the part of this frame, that can hold main data
+    #[must_use]
+    pub fn main_data_area(&self) -> Range<usize> {
+        (self.range.start + self.header.side_info_offset() + self.header.side_info_len())
+            ..self.range.end
+    }
+    /// checks if this frame is a Xing/Info frame, that just contains metadata of the stream
+    #[must_use]
+    pub fn is_info_frame(&self, stream_data: &[u8]) -> bool {
+        let offset = self.header.side_info_offset() + self.header.side_info_len();
+        self.data(stream_data)
+            .get(offset..offset + 4)
+            .is_some_and(|tag| tag == b"Xing" || tag == b"Info")
+    }
+}
+
+/// the frames of a mp3 file, without any tags
+#[derive(Debug)]
+pub struct Mp3Stream {
+    data: Vec<u8>,
+    frames: Vec<Frame>,
+    sample_rate: u32,
+    gapless: Option<Gapless>,
+}
+impl Mp3Stream {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|err| Error::IO(path.to_path_buf(), err))?;
+        Self::from_data(data)
+    }
+
+    pub fn from_data(data: Vec<u8>) -> Result<Self, Error> {
+        let mut frames = Vec::new();
+        let mut pos = id3v2_len(&data);
+        let mut sample_rate = None;
+        while pos + FrameHeader::SIZE <= data.len() {
+            let Some(header) = FrameHeader::parse(&data[pos..]) else {
+                pos += 1;
+                continue;
+            };
+            let end = pos + header.frame_len();
+            if end > data.len() {
+                debug!("dropping truncated frame at byte {pos}");
+                break;
+            }
+            // a false sync is likely, when the next frame doesn't start with a header
+            if end + FrameHeader::SIZE <= data.len()
+                && FrameHeader::parse(&data[end..]).is_none()
+                && !is_tag_start(&data[end..])
+            {
+                trace!("ignoring false sync at byte {pos}");
+                pos += 1;
+                continue;
+            }
+            match sample_rate {
+                None => sample_rate = Some(header.sample_rate),
+                Some(sr) if sr != header.sample_rate => {
+                    return Err(Error::SampleRateChanged(sr, header.sample_rate, pos));
+                }
+                Some(_) => {}
+            }
+            frames.push(Frame {
+                header,
+                range: pos..end,
+            });
+            pos = end;
+        }
+        let sample_rate = sample_rate.ok_or(Error::NoFrames)?;
+        let mut stream = Self {
+            data,
+            frames,
+            sample_rate,
+            gapless: None,
+        };
+        if stream
+            .frames
+            .first()
+            .is_some_and(|frame| frame.is_info_frame(&stream.data))
+        {
+            trace!("dropping Xing/Info frame");
+            let info = stream.frames.remove(0);
+            stream.gapless = read_gapless(info.data(&stream.data), &info.header);
+            debug!("read gapless info {:?}", stream.gapless);
+        }
+        Ok(stream)
+    }
+
+    #[must_use]
+    pub const fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+    /// the encoder delay and padding of the original stream, if known
+    #[must_use]
+    pub const fn gapless(&self) -> Option<Gapless> {
+        self.gapless
+    }
+    #[must_use]
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.header.duration()).sum()
+    }
+
+    /// the indices of all frames, that start in the time range `start..end`
+    #[must_use]
+    pub fn frame_range(&self, start: Duration, end: Duration) -> Range<usize> {
+        let mut time = Duration::ZERO;
+        let mut first = None;
+        for (i, frame) in self.frames.iter().enumerate() {
+            if first.is_none() && time >= start {
+                first = Some(i);
+            }
+            if time >= end {
+                return first.unwrap_or(i)..i;
+            }
+            time += frame.header.duration();
+        }
+        if end > time + Duration::from_millis(100) {
+            warn!("requested end {end:?} is after the end of the stream {time:?}");
+        }
+        first.unwrap_or(self.frames.len())..self.frames.len()
+    }
+
+    /// the main data of the frame with index `frame`, or `None` when it references data before
+    /// the start of the stream
+    #[must_use]
+    pub fn main_data(&self, frame: usize) -> Option<Vec<u8>> {
+        let current = &self.frames[frame];
+        let side_info = current.side_info(&self.data);
+        let begin = current.header.main_data_begin(side_info);
+        let len = current.header.main_data_len(side_info);
+
+        // collect the bytes before this frame from the areas of the previous frames
+        let mut before = Vec::new();
+        let mut previous = frame;
+        while before.len() < begin {
+            previous = previous.checked_sub(1)?;
+            let area = &self.data[self.frames[previous].main_data_area()];
+            let missing = begin - before.len();
+            let mut chunk = area[area.len() - area.len().min(missing)..].to_vec();
+            chunk.append(&mut before);
+            before = chunk;
+        }
+        let mut data = before;
+        let mut areas = self.frames[frame..]
+            .iter()
+            .map(|frame| &self.data[frame.main_data_area()]);
+        while data.len() < begin + len {
+            let area = areas.next()?;
+            let take = area.len().min(begin + len - data.len());
+            data.extend_from_slice(&area[..take]);
+        }
+        data.drain(..begin);
+        Some(data)
+    }
+}
+
+/// the length of a ID3v2 tag at the start of `data`
+fn id3v2_len(data: &[u8]) -> usize {
+    match data {
+        [b'I', b'D', b'3', _, _, flags, s0, s1, s2, s3, ..] => {
+            let size = [s0, s1, s2, s3]
+                .into_iter()
+                .fold(0usize, |acc, &b| (acc << 7) | (b & 0x7F) as usize);
+            let footer = if flags & 0x10 == 0 { 0 } else { 10 };
+            10 + size + footer
+        }
+        _ => 0,
+    }
+}
+/// checks if `data` starts with a tag, that can follow the last frame
+fn is_tag_start(data: &[u8]) -> bool {
+    data.starts_with(b"TAG") || data.starts_with(b"ID3") || data.starts_with(b"APETAGEX")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// MPEG1 Layer III, 128 kbit/s, 44.1 kHz, joint stereo, no crc
+    pub(crate) const HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x44];
+
+    pub(crate) fn test_data(frames: usize) -> Vec<u8> {
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        let mut data = Vec::new();
+        for i in 0..frames {
+            data.extend(HEADER);
+            data.extend(std::iter::repeat(i as u8).take(header.frame_len() - FrameHeader::SIZE));
+        }
+        data
+    }
+
+    #[test]
+    fn parse_header() {
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        assert_eq!(Version::Mpeg1, header.version);
+        assert_eq!(128, header.bitrate);
+        assert_eq!(44100, header.sample_rate);
+        assert_eq!(ChannelMode::JointStereo, header.channel_mode);
+        assert!(!header.has_crc);
+        assert!(!header.padding);
+        assert_eq!(417, header.frame_len());
+        assert_eq!(1152, header.samples_per_frame());
+
+        assert_eq!(None, FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x44]), "bad bitrate");
+        assert_eq!(None, FrameHeader::parse(&[0xFF, 0xFD, 0x90, 0x44]), "layer II");
+        assert_eq!(None, FrameHeader::parse(&[0xFF, 0xFB]), "too short");
+    }
+
+    #[test]
+    fn read_frames() {
+        let mut data = vec![b'I', b'D', b'3', 4, 0, 0, 0, 0, 0, 2, 0, 0];
+        data.extend(test_data(5));
+        let stream = Mp3Stream::from_data(data).unwrap();
+        assert_eq!(5, stream.frames().len());
+        assert_eq!(12..12 + 417, stream.frames()[0].range);
+        assert_eq!(44100, stream.sample_rate());
+    }
+
+    #[test]
+    fn frame_ranges() {
+        let stream = Mp3Stream::from_data(test_data(10)).unwrap();
+        let frame = FrameHeader::parse(&HEADER).unwrap().duration();
+        assert_eq!(2..5, stream.frame_range(frame * 2, frame * 5));
+        assert_eq!(3..5, stream.frame_range(frame * 2 + frame / 2, frame * 5));
+        assert_eq!(8..10, stream.frame_range(frame * 8, frame * 20));
+    }
+
+    #[test]
+    fn main_data_begin() {
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        let mut side_info = [0xFF; 32];
+        header.set_main_data_begin(&mut side_info, 0b1_0101_0101);
+        assert_eq!(0b1_0101_0101, header.main_data_begin(&side_info));
+        assert_eq!([0b1010_1010, 0b1111_1111], side_info[..2]);
+        header.set_main_data_begin(&mut side_info, 0);
+        assert_eq!([0, 0b0111_1111], side_info[..2]);
+    }
+
+    #[test]
+    fn main_data_len() {
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        let mut side_info = [0; 32];
+        assert_eq!(0, header.main_data_len(&side_info));
+        // part2_3_length of the first granule and channel starts at bit 20
+        side_info[3] = 0b0000_1000;
+        assert_eq!(1, header.main_data_len(&side_info), "8 bits");
+    }
+}
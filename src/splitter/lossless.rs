@@ -0,0 +1,431 @@
+//! cuts and joins mp3 streams at frame boundaries without reencoding.
+//!
+//! The main data of each frame is extracted from the bit reservoir of the source and repacked
+//! into the new stream, so frames after a cut or join point stay decodable. Each written stream
+//! starts with a new Xing/Info frame, which contains the encoder delay and padding needed to
+//! make the start and end sample accurate.
+use std::time::Duration;
+
+use itertools::{Itertools, Position};
+use log::{debug, warn};
+use thiserror::Error;
+
+use super::{
+    frame::{FrameHeader, Mp3Stream},
+    xing::{self, Gapless},
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("nothing to write, all parts are empty")]
+    Empty,
+    #[error("the parts have incompatible formats")]
+    Incompatible,
+    #[error("main data of {0} bytes doesn't fit into the bit reservoir")]
+    ReservoirOverflow(usize),
+}
+
+/// a time range of a stream, that should be written
+#[derive(Debug, Clone, Copy)]
+pub struct Part<'s> {
+    pub stream: &'s Mp3Stream,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// the number of silent frames, that may be added to fill the bit reservoir of the first frames
+const MAX_SILENT_FRAMES: usize = 4;
+
+/// a frame, that still needs to be placed in the new stream
+#[derive(Debug, Clone)]
+struct PendingFrame {
+    /// header without crc
+    header: [u8; 4],
+    side_info: Vec<u8>,
+    main_data: Vec<u8>,
+}
+impl PendingFrame {
+    /// a frame with zeroed side info, that decodes to silence
+    fn silent(header: [u8; 4]) -> Self {
+        let parsed = FrameHeader::parse(&header).expect("need valid header");
+        Self {
+            header,
+            side_info: vec![0; parsed.side_info_len()],
+            main_data: Vec::new(),
+        }
+    }
+}
+
+/// the audio frames of a new stream
+#[derive(Debug)]
+struct LaidOut {
+    data: Vec<u8>,
+    frame_lens: Vec<usize>,
+    is_vbr: bool,
+}
+
+/// copies `start..end` of `stream` without reencoding
+pub fn cut(stream: &Mp3Stream, start: Duration, end: Duration) -> Result<Vec<u8>, Error> {
+    join([Part { stream, start, end }])
+}
+
+/// concatenates `parts` without reencoding.
+///
+/// The start of the first and the end of the last part are sample accurate, the boundaries
+/// between the parts are rounded to the nearest frame.
+pub fn join<'s>(parts: impl IntoIterator<Item = Part<'s>>) -> Result<Vec<u8>, Error> {
+    let mut frames = Vec::new();
+    let mut template = None::<FrameHeader>;
+    let mut skipped_samples = 0;
+    let mut padding = 0;
+    for (pos, part) in parts.into_iter().with_position() {
+        let is_first = matches!(pos, Position::First | Position::Only);
+        let is_last = matches!(pos, Position::Last | Position::Only);
+        let Some(first) = part.stream.frames().first() else {
+            continue;
+        };
+        match &template {
+            Some(template) if !template.is_compatible(&first.header) => {
+                return Err(Error::Incompatible)
+            }
+            Some(_) => {}
+            None => template = Some(first.header),
+        }
+        let spf = first.header.samples_per_frame();
+        let selection = select_frames(part, spf, is_first, is_last);
+        debug!("using frames {selection:?} for {part:?}");
+        if is_first {
+            skipped_samples += selection.skip_start;
+        }
+        if is_last {
+            padding = selection.skip_end;
+        }
+        for i in selection.frames {
+            frames.push(pending_frame(part.stream, i));
+        }
+    }
+    let template = template.ok_or(Error::Empty)?;
+    let Some(first) = frames.first() else {
+        return Err(Error::Empty);
+    };
+    let silent_header = first.header;
+
+    let mut silent = 0;
+    let laid_out = loop {
+        match layout(&frames, silent, silent_header) {
+            Ok(laid_out) => break laid_out,
+            Err(_) if silent < MAX_SILENT_FRAMES => silent += 1,
+            Err(len) => return Err(Error::ReservoirOverflow(len)),
+        }
+    };
+    if silent > 0 {
+        debug!("added {silent} silent frames to fill the bit reservoir");
+    }
+    skipped_samples += silent * template.samples_per_frame();
+    if skipped_samples > xing::MAX_GAPLESS as usize {
+        warn!("can't skip {skipped_samples} samples at the start, the stream will start early");
+    }
+
+    let info_header = std::iter::once(silent_header)
+        .chain(
+            FrameHeader::bitrate_indices()
+                .map(|index| FrameHeader::with_bitrate_index(silent_header, index)),
+        )
+        .find(|bytes| {
+            let header = FrameHeader::parse(bytes).expect("need valid header");
+            header.frame_len() >= xing::needed_len(&header)
+        })
+        .expect("the highest bitrate can hold the info tag");
+    let mut out = xing::info_frame(
+        info_header,
+        &laid_out.frame_lens,
+        laid_out.is_vbr,
+        Gapless {
+            delay: skipped_samples.min(xing::MAX_GAPLESS as usize) as u16,
+            padding: padding.min(xing::MAX_GAPLESS as usize) as u16,
+        },
+        xing::crc16_lame(&laid_out.data),
+    );
+    out.extend(laid_out.data);
+    Ok(out)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Selection {
+    frames: std::ops::Range<usize>,
+    /// number of samples, that should be skipped at the start of `frames`
+    skip_start: usize,
+    /// number of samples, that should be skipped at the end of `frames`
+    skip_end: usize,
+}
+/// selects the frames of `part`, adding a frame before the start to prime the decoder
+fn select_frames(part: Part, spf: usize, is_first: bool, is_last: bool) -> Selection {
+    let stream = part.stream;
+    let delay = stream.gapless().map_or(0, |it| it.delay as usize);
+    let to_sample = |time: Duration| {
+        (time.as_secs_f64() * stream.sample_rate() as f64).round() as usize + delay
+    };
+    let frame_count = stream.frames().len();
+    let (start, end) = (to_sample(part.start), to_sample(part.end));
+
+    let (mut first, mut skip_start) = if is_first {
+        (start / spf, start % spf)
+    } else {
+        ((start + spf / 2) / spf, 0)
+    };
+    let last = if is_last {
+        end.div_ceil(spf)
+    } else {
+        (end + spf / 2) / spf
+    }
+    .min(frame_count);
+    let skip_end = if is_last {
+        (last * spf).saturating_sub(end)
+    } else {
+        0
+    };
+    first = first.min(last);
+    if is_first && first > 0 && first < last {
+        // the first decoded frame isn't complete, because the overlap of the previous is missing
+        first -= 1;
+        skip_start += spf;
+    }
+    Selection {
+        frames: first..last,
+        skip_start,
+        skip_end,
+    }
+}
+
+fn pending_frame(stream: &Mp3Stream, i: usize) -> PendingFrame {
+    let frame = &stream.frames()[i];
+    let mut header = frame.header_bytes(stream.data());
+    header[1] |= 1; // crc isn't written, so the side info can change
+    match stream.main_data(i) {
+        Some(main_data) => PendingFrame {
+            header,
+            side_info: frame.side_info(stream.data()).to_vec(),
+            main_data,
+        },
+        None => {
+            warn!("main data of frame {i} starts before the stream, replacing it with silence");
+            PendingFrame::silent(header)
+        }
+    }
+}
+
+/// writes `silent` silent frames followed by `frames`, while placing the main data as early as
+/// possible in the bit reservoir. When the main data of a frame doesn't fit, the bitrate of the
+/// frame is raised.
+///
+/// returns the length of the main data, that didn't fit, on failure
+fn layout(frames: &[PendingFrame], silent: usize, silent_header: [u8; 4]) -> Result<LaidOut, usize> {
+    let silent_frame = PendingFrame::silent(silent_header);
+    let mut reservoir = Vec::<u8>::new();
+    let mut data_end = 0;
+    let mut placed = Vec::with_capacity(silent + frames.len());
+    for frame in std::iter::repeat(&silent_frame).take(silent).chain(frames) {
+        let original = FrameHeader::parse(&frame.header).expect("need valid header");
+        let len = frame.main_data.len();
+        let area_start = reservoir.len();
+        let start = data_end.max(area_start.saturating_sub(original.max_main_data_begin()));
+
+        let (header_bytes, header) = std::iter::once(frame.header)
+            .chain(
+                FrameHeader::bitrate_indices()
+                    .map(|index| FrameHeader::with_bitrate_index(frame.header, index)),
+            )
+            .map(|bytes| (bytes, FrameHeader::parse(&bytes).expect("need valid header")))
+            .filter(|(_, header)| header == &original || header.bitrate > original.bitrate)
+            .find(|(_, header)| start + len <= area_start + header.main_data_capacity())
+            .ok_or(len)?;
+        if header != original {
+            debug!(
+                "raised bitrate from {} to {} to fit main data",
+                original.bitrate, header.bitrate
+            );
+        }
+
+        reservoir.resize(area_start + header.main_data_capacity(), 0);
+        reservoir[start..start + len].copy_from_slice(&frame.main_data);
+        data_end = start + len;
+
+        let mut side_info = frame.side_info.clone();
+        header.set_main_data_begin(&mut side_info, area_start - start);
+        placed.push((header_bytes, header, side_info, area_start..reservoir.len()));
+    }
+
+    let is_vbr = !placed
+        .iter()
+        .map(|(_, header, _, _)| header.bitrate)
+        .all_equal();
+    let mut data = Vec::new();
+    let mut frame_lens = Vec::with_capacity(placed.len());
+    for (header_bytes, header, side_info, area) in placed {
+        let start = data.len();
+        data.extend(header_bytes);
+        data.extend(side_info);
+        data.extend_from_slice(&reservoir[area]);
+        debug_assert_eq!(header.frame_len(), data.len() - start, "wrong frame length");
+        frame_lens.push(data.len() - start);
+    }
+    Ok(LaidOut {
+        data,
+        frame_lens,
+        is_vbr,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splitter::frame::tests::HEADER;
+
+    /// builds a stream of frames with `(main_data_begin, main_data_len)`,
+    /// the main data of frame `i` is filled with `i + 1`
+    fn build(spec: &[(usize, usize)]) -> Mp3Stream {
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        let capacity = header.main_data_capacity();
+        let mut areas = vec![0u8; capacity * spec.len()];
+        let mut side_infos = Vec::new();
+        for (i, &(begin, len)) in spec.iter().enumerate() {
+            let start = i * capacity - begin;
+            areas[start..start + len].fill(i as u8 + 1);
+
+            let mut side_info = vec![0; header.side_info_len()];
+            header.set_main_data_begin(&mut side_info, begin);
+            // part2_3_length of the first granule and channel uses bits 20..32
+            let bits = len * 8;
+            side_info[2] |= (bits >> 8) as u8;
+            side_info[3] = (bits & 0xFF) as u8;
+            side_infos.push(side_info);
+        }
+        let mut data = Vec::new();
+        for (side_info, area) in side_infos.into_iter().zip(areas.chunks(capacity)) {
+            data.extend(HEADER);
+            data.extend(side_info);
+            data.extend_from_slice(area);
+        }
+        Mp3Stream::from_data(data).unwrap()
+    }
+
+    fn main_data(stream: &Mp3Stream) -> Vec<Vec<u8>> {
+        (0..stream.frames().len())
+            .map(|i| stream.main_data(i).expect("main data before stream"))
+            .collect()
+    }
+
+    fn frame_duration() -> Duration {
+        FrameHeader::parse(&HEADER).unwrap().duration()
+    }
+
+    /// frame 1 and 2 use the bit reservoir
+    const SPEC: [(usize, usize); 4] = [(0, 200), (181, 500), (62, 100), (0, 300)];
+
+    #[test]
+    fn read_reservoir() {
+        let stream = build(&SPEC);
+        assert_eq!(
+            vec![vec![1; 200], vec![2; 500], vec![3; 100], vec![4; 300]],
+            main_data(&stream)
+        );
+    }
+
+    #[test]
+    fn cut_keeps_main_data() {
+        let source = build(&SPEC);
+        let cut = cut(&source, frame_duration() * 2, frame_duration() * 4).unwrap();
+        let stream = Mp3Stream::from_data(cut).unwrap();
+
+        // frame 1 is added for the decoder, and needs a higher bitrate without frame 0
+        assert_eq!(
+            vec![vec![2; 500], vec![3; 100], vec![4; 300]],
+            main_data(&stream)
+        );
+        assert!(stream.frames().first().unwrap().header.bitrate > 128);
+        assert_eq!(
+            Some(Gapless {
+                delay: 1152,
+                padding: 0
+            }),
+            stream.gapless()
+        );
+    }
+
+    #[test]
+    fn cut_sample_accurate() {
+        let source = build(&SPEC);
+        let sample = Duration::from_secs(1) / 44100;
+        let cut = cut(&source, sample * 100, frame_duration() * 2 + sample * 52).unwrap();
+        let stream = Mp3Stream::from_data(cut).unwrap();
+
+        assert_eq!(
+            vec![vec![1; 200], vec![2; 500], vec![3; 100]],
+            main_data(&stream)
+        );
+        assert_eq!(
+            Some(Gapless {
+                delay: 100,
+                padding: 1100
+            }),
+            stream.gapless()
+        );
+    }
+
+    #[test]
+    fn join_parts() {
+        let source = build(&SPEC);
+        let frame = frame_duration();
+        let joined = join([
+            Part {
+                stream: &source,
+                start: Duration::ZERO,
+                end: frame,
+            },
+            Part {
+                stream: &source,
+                start: frame * 2,
+                end: frame * 4,
+            },
+        ])
+        .unwrap();
+        let stream = Mp3Stream::from_data(joined).unwrap();
+        assert_eq!(
+            vec![vec![1; 200], vec![3; 100], vec![4; 300]],
+            main_data(&stream)
+        );
+        assert_eq!(Some(Gapless::default()), stream.gapless());
+    }
+
+    #[test]
+    fn raise_bitrate() {
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        let big = PendingFrame {
+            header: HEADER,
+            side_info: vec![0; header.side_info_len()],
+            main_data: vec![1; header.main_data_capacity() * 2],
+        };
+        let laid_out = layout(&[big, PendingFrame::silent(HEADER)], 0, HEADER).unwrap();
+        assert!(laid_out.is_vbr);
+        assert_eq!(
+            vec![835, header.frame_len()],
+            laid_out.frame_lens,
+            "first frame should use 256 kbps"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let source = build(&SPEC);
+        assert_eq!(
+            Err(Error::Empty),
+            join(std::iter::empty::<Part>()),
+            "no parts"
+        );
+        assert_eq!(
+            Err(Error::Empty),
+            cut(&source, frame_duration() * 10, frame_duration() * 20),
+            "after the end"
+        );
+    }
+}
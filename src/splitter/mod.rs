@@ -0,0 +1,272 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use audacity::data::TimeLabel;
+use log::{debug, info, warn};
+use thiserror::Error;
+
+use crate::{
+    archive::data::{build_timelabel_name, Archive},
+    worker::{
+        tagger::{self, Album, Genre, TaggedFile, Title, Track},
+        template::sanitize,
+    },
+};
+
+pub mod args;
+pub mod frame;
+pub mod lossless;
+pub mod recording;
+pub mod xing;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Frame(#[from] frame::Error),
+    #[error("couldn't cut {0:?}, because {1}")]
+    Cut(PathBuf, #[source] lossless::Error),
+    #[error("couldn't read labels from {0:?}")]
+    Labels(PathBuf),
+    #[error("couldn't write {0:?}, because {1}")]
+    Write(PathBuf, #[source] std::io::Error),
+    #[error("couldn't write labels to {0:?}")]
+    WriteLabels(PathBuf),
+    #[error("couldn't encode {0:?}, because {1}")]
+    Encode(PathBuf, String),
+    #[error("id3 Error {1} for {0:?}")]
+    Tag(PathBuf, #[source] tagger::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// cuts the frames of the recording without reencoding
+    Mp3,
+    /// encodes the parts with ffmpeg
+    Opus,
+}
+impl Format {
+    #[must_use]
+    pub const fn ext(self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::Opus => "opus",
+        }
+    }
+}
+
+pub fn run(args: &args::Arguments) -> Result<(), Error> {
+    debug!("{args:#?}");
+    let labels = read_labels(args.label_path())?;
+    let written = split(
+        &args.recording,
+        &labels,
+        args.out_dir(),
+        args.format,
+        args.genre.as_deref(),
+        args.dry_run,
+    )?;
+    info!("exported {} files", written.len());
+    Ok(())
+}
+
+pub fn read_labels(path: impl AsRef<Path>) -> Result<Vec<TimeLabel>, Error> {
+    let path = path.as_ref();
+    Ok(TimeLabel::read(path)
+        .map_err(|_| Error::Labels(path.to_path_buf()))?
+        .into_iter()
+        .collect())
+}
+
+/// the metadata of an exported label, read from its name
+#[derive(Debug, PartialEq, Eq)]
+pub struct LabelInfo<'a> {
+    pub file_name: OsString,
+    pub series: Option<&'a str>,
+    pub chapter_number: Option<u32>,
+    pub title: &'a str,
+}
+impl<'a> LabelInfo<'a> {
+    /// reads the name of `label`, returns `None` for unnamed labels and labels starting with '#'
+    #[must_use]
+    pub fn from_label(label: &'a TimeLabel, ext: &str) -> Option<Self> {
+        let name = label.name().filter(|name| !name.is_empty())?;
+        if name.starts_with('#') {
+            debug!("skipping {name:?}");
+            return None;
+        }
+        let mut info = match Archive::parse_line(name) {
+            Some((series, nr, part, chapter)) => Self {
+                file_name: sanitize(&build_timelabel_name::<str, _, _>(
+                    series, &nr, part, chapter,
+                ))
+                .into(),
+                series: Some(series),
+                chapter_number: Some(nr.nr as u32),
+                title: chapter.unwrap_or(name),
+            },
+            None => {
+                warn!("couldn't parse {name:?}, using it as title");
+                Self {
+                    file_name: sanitize(name).into(),
+                    series: None,
+                    chapter_number: None,
+                    title: name,
+                }
+            }
+        };
+        // push extension manually, because the part number looks like an extension
+        info.file_name.push(".");
+        info.file_name.push(ext);
+        Some(info)
+    }
+}
+
+/// writes one file for each label in `labels` into `out_dir`, returns the paths of the written files
+pub fn split(
+    recording: impl AsRef<Path>,
+    labels: &[TimeLabel],
+    out_dir: impl AsRef<Path>,
+    format: Format,
+    genre: Option<&str>,
+    dry_run: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let recording = recording.as_ref();
+    let stream = match format {
+        Format::Mp3 => Some(frame::Mp3Stream::read(recording)?),
+        Format::Opus => None,
+    };
+    let mut written = Vec::new();
+    for label in labels {
+        let Some(info) = LabelInfo::from_label(label, format.ext()) else {
+            continue;
+        };
+        let path = out_dir.as_ref().join(&info.file_name);
+        if dry_run {
+            info!(
+                "would export {:?}..{:?} to {}",
+                label.start(),
+                label.end(),
+                path.display()
+            );
+            written.push(path);
+            continue;
+        }
+        debug!("exporting {}", path.display());
+        match &stream {
+            Some(stream) => {
+                let data = lossless::cut(stream, *label.start(), *label.end())
+                    .map_err(|err| Error::Cut(path.clone(), err))?;
+                std::fs::write(&path, data).map_err(|err| Error::Write(path.clone(), err))?;
+            }
+            None => encode_opus(recording, *label.start(), *label.end(), &path)?,
+        }
+        write_tags(&path, &info, genre)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// encodes `start..end` of `recording` with ffmpeg
+fn encode_opus(
+    recording: &Path,
+    start: Duration,
+    end: Duration,
+    path: &Path,
+) -> Result<(), Error> {
+    let output = std::process::Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-y", "-ss"])
+        .arg(format!("{:.3}", start.as_secs_f64()))
+        .arg("-t")
+        .arg(format!("{:.3}", end.saturating_sub(start).as_secs_f64()))
+        .arg("-i")
+        .arg(recording)
+        .args(["-vn", "-map_metadata", "-1", "-c:a", "libopus"])
+        .arg(path)
+        .output()
+        .map_err(|err| Error::Encode(path.to_path_buf(), err.to_string()))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::Encode(
+            path.to_path_buf(),
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ))
+    }
+}
+
+fn write_tags(path: &Path, info: &LabelInfo, genre: Option<&str>) -> Result<(), Error> {
+    let map_err = |err| Error::Tag(path.to_path_buf(), err);
+    let mut tag = TaggedFile::new_empty(path.to_path_buf()).map_err(map_err)?;
+    tag.set::<Title>(info.title);
+    tag.set::<Album>(info.series);
+    tag.set::<Track>(info.chapter_number);
+    tag.set::<Genre>(genre);
+    tag.save_changes(true).map_err(map_err)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_info() {
+        let label = TimeLabel::new(
+            Duration::ZERO,
+            Duration::from_secs(1),
+            Some("Gruselkabinett 6.2 Das verfluchte Haus"),
+        );
+        assert_eq!(
+            Some(LabelInfo {
+                file_name: "Gruselkabinett 6.2 Das verfluchte Haus.mp3".into(),
+                series: Some("Gruselkabinett"),
+                chapter_number: Some(6),
+                title: "Das verfluchte Haus",
+            }),
+            LabelInfo::from_label(&label, "mp3")
+        );
+    }
+
+    #[test]
+    fn label_info_unparsed() {
+        let label = TimeLabel::new(Duration::ZERO, Duration::from_secs(1), Some("Werbung"));
+        assert_eq!(
+            Some(LabelInfo {
+                file_name: "Werbung.opus".into(),
+                series: None,
+                chapter_number: None,
+                title: "Werbung",
+            }),
+            LabelInfo::from_label(&label, "opus")
+        );
+    }
+
+    #[test]
+    fn label_info_sanitized() {
+        let label = TimeLabel::new(
+            Duration::ZERO,
+            Duration::from_secs(1),
+            Some("Gruselkabinett 6 Das Haus: Teil 1/2"),
+        );
+        assert_eq!(
+            "Gruselkabinett 6 Das Haus_ Teil 1_2.mp3",
+            LabelInfo::from_label(&label, "mp3").unwrap().file_name
+        );
+        let label = TimeLabel::new(Duration::ZERO, Duration::from_secs(1), Some("Werbung?"));
+        assert_eq!(
+            "Werbung_.mp3",
+            LabelInfo::from_label(&label, "mp3").unwrap().file_name
+        );
+    }
+
+    #[test]
+    fn label_info_skipped() {
+        let comment = TimeLabel::new(Duration::ZERO, Duration::from_secs(1), Some("# comment"));
+        assert_eq!(None, LabelInfo::from_label(&comment, "mp3"));
+        let unnamed = TimeLabel::new::<&str>(Duration::ZERO, Duration::from_secs(1), None);
+        assert_eq!(None, LabelInfo::from_label(&unnamed, "mp3"));
+    }
+}
@@ -0,0 +1,328 @@
+//! recordings, that the recorder split into several files like `name (1).mp3`, `name (2).mp3`
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use audacity::data::TimeLabel;
+use log::{debug, info};
+use regex::Regex;
+
+use super::{
+    frame::Mp3Stream,
+    lossless::{self, Part},
+    Error,
+};
+
+/// labels this close to the boundary of two files are considered to cross it
+const BOUNDARY_TOLERANCE: Duration = Duration::from_secs(1);
+
+/// one continuous recording, made of one or more files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    /// the path without the number of the part, names the label file
+    path: PathBuf,
+    /// the files in the order they were recorded
+    parts: Vec<PathBuf>,
+}
+
+/// splits `path` into the path without the number of the part and that number
+fn split_number(path: &Path) -> Option<(PathBuf, usize)> {
+    lazy_static::lazy_static! {
+        static ref RE: Regex = Regex::new(r"^(.*) \((\d+)\)$").unwrap();
+    }
+    let captures = RE.captures(path.file_stem()?.to_str()?)?;
+    let mut file_name = OsString::from(&captures[1]);
+    if let Some(ext) = path.extension() {
+        // push extension manually, because the name might contain a '.'
+        file_name.push(".");
+        file_name.push(ext);
+    }
+    Some((path.with_file_name(file_name), captures[2].parse().ok()?))
+}
+
+impl Recording {
+    /// groups `paths` into recordings, keeping the order of their first files.
+    /// The parts of a recording are sorted by their number, a file without number comes first
+    pub fn group(paths: impl IntoIterator<Item = PathBuf>) -> Vec<Self> {
+        let mut groups = Vec::<(PathBuf, Vec<(usize, PathBuf)>)>::new();
+        for path in paths {
+            let (main, nr) = split_number(&path).unwrap_or_else(|| (path.clone(), 0));
+            match groups.iter_mut().find(|(it, _)| *it == main) {
+                Some((_, parts)) => parts.push((nr, path)),
+                None => groups.push((main, vec![(nr, path)])),
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(main, mut parts)| {
+                parts.sort_by_key(|(nr, _)| *nr);
+                let mut parts = parts.into_iter().map(|(_, path)| path).collect::<Vec<_>>();
+                parts.dedup();
+                // a single file keeps its name, it might just contain a number
+                let path = if parts.len() == 1 {
+                    parts[0].clone()
+                } else {
+                    main
+                };
+                Self { path, parts }
+            })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    #[must_use]
+    pub fn parts(&self) -> &[PathBuf] {
+        &self.parts
+    }
+    #[must_use]
+    pub fn is_split(&self) -> bool {
+        self.parts.len() > 1
+    }
+    /// the label file of the whole recording
+    #[must_use]
+    pub fn label_path(&self) -> PathBuf {
+        self.path.with_extension("txt")
+    }
+    /// a single file in `dir` with the audio of all parts, created by [`Recording::join`]
+    #[must_use]
+    pub fn joined_path(&self, dir: &Path) -> PathBuf {
+        if !self.is_split() {
+            return self.path.clone();
+        }
+        let mut file_name = self.path.file_stem().unwrap_or_default().to_owned();
+        file_name.push(" (joined)");
+        if let Some(ext) = self.path.extension() {
+            file_name.push(".");
+            file_name.push(ext);
+        }
+        dir.join(file_name)
+    }
+
+    /// writes the audio of all parts into [`Recording::joined_path`] without reencoding,
+    /// if it doesn't exist yet. Returns the path of the joined file
+    pub fn join(&self, dir: &Path, dry_run: bool) -> Result<PathBuf, Error> {
+        let joined = self.joined_path(dir);
+        if !self.is_split() || joined.exists() {
+            return Ok(joined);
+        }
+        if dry_run {
+            info!("dry run, not joining {:?}", self.parts);
+            return Ok(joined);
+        }
+        debug!("joining {:?} into {joined:?}", self.parts);
+        let streams = self
+            .parts
+            .iter()
+            .map(Mp3Stream::read)
+            .collect::<Result<Vec<_>, _>>()?;
+        let data = lossless::join(streams.iter().map(|stream| Part {
+            stream,
+            start: Duration::ZERO,
+            end: stream.duration(),
+        }))
+        .map_err(|err| Error::Cut(joined.clone(), err))?;
+        // an interrupted write mustn't leave a truncated file, that is taken as joined later
+        let mut tmp = joined.as_os_str().to_owned();
+        tmp.push(".part");
+        let tmp = PathBuf::from(tmp);
+        std::fs::write(&tmp, data).map_err(|err| Error::Write(tmp.clone(), err))?;
+        std::fs::rename(&tmp, &joined).map_err(|err| Error::Write(joined.clone(), err))?;
+        Ok(joined)
+    }
+
+    /// reads the labels of the whole recording.
+    /// Without a label file for the whole recording, the label files of the parts are combined
+    pub fn read_labels(&self) -> Result<Vec<TimeLabel>, Error> {
+        let label_path = self.label_path();
+        if !self.is_split() || label_path.exists() {
+            return super::read_labels(label_path);
+        }
+        let mut parts = Vec::new();
+        for part in &self.parts {
+            let labels = super::read_labels(part.with_extension("txt"))?;
+            parts.push((Mp3Stream::read(part)?.duration(), labels));
+        }
+        Ok(combine_labels(parts))
+    }
+
+    /// writes `labels` of the whole recording into the label files of the parts
+    pub fn write_part_labels(&self, labels: &[TimeLabel], dry_run: bool) -> Result<(), Error> {
+        let durations = self
+            .parts
+            .iter()
+            .map(|part| Mp3Stream::read(part).map(|it| it.duration()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (part, labels) in self.parts.iter().zip(split_labels(durations, labels)) {
+            let label_path = part.with_extension("txt");
+            TimeLabel::write(labels, &label_path, dry_run)
+                .map_err(|_| Error::WriteLabels(label_path))?;
+        }
+        Ok(())
+    }
+}
+
+/// moves the labels of each part behind the previous parts, each part is given with its duration.
+/// A label ending at the end of a part is merged with one starting at the start of the next part
+#[must_use]
+pub fn combine_labels(
+    parts: impl IntoIterator<Item = (Duration, Vec<TimeLabel>)>,
+) -> Vec<TimeLabel> {
+    let mut combined = Vec::<TimeLabel>::new();
+    let mut offset = Duration::ZERO;
+    // if the last label of the previous part reached its end
+    let mut is_open = false;
+    for (duration, labels) in parts {
+        for (i, label) in labels.iter().enumerate() {
+            let start = *label.start() + offset;
+            let end = *label.end() + offset;
+            if i == 0 && is_open && *label.start() <= BOUNDARY_TOLERANCE {
+                let last = combined.pop().expect("open label exists");
+                combined.push(TimeLabel::new(
+                    *last.start(),
+                    end,
+                    last.name().or_else(|| label.name()),
+                ));
+                continue;
+            }
+            combined.push(TimeLabel::new(start, end, label.name()));
+        }
+        is_open = labels
+            .last()
+            .is_some_and(|label| *label.end() + BOUNDARY_TOLERANCE >= duration);
+        offset += duration;
+    }
+    combined
+}
+
+/// the reverse of [`combine_labels`], moves `labels` into the parts with the given durations.
+/// A label crossing the end of a part is cut into one label for each part
+#[must_use]
+pub fn split_labels(
+    durations: impl IntoIterator<Item = Duration>,
+    labels: &[TimeLabel],
+) -> Vec<Vec<TimeLabel>> {
+    let mut offset = Duration::ZERO;
+    durations
+        .into_iter()
+        .map(|duration| {
+            let start = offset;
+            let end = offset + duration;
+            offset = end;
+            labels
+                .iter()
+                .filter(|label| *label.start() < end && *label.end() > start)
+                .map(|label| {
+                    TimeLabel::new(
+                        (*label.start()).max(start) - start,
+                        (*label.end()).min(end) - start,
+                        label.name(),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn group_parts() {
+        let recordings = Recording::group(
+            [
+                "rec/a (2).mp3",
+                "rec/b.mp3",
+                "rec/a (1).mp3",
+                "rec/c (1).mp3",
+                "rec/a.mp3",
+            ]
+            .map(PathBuf::from),
+        );
+        assert_eq!(
+            vec![
+                Recording {
+                    path: PathBuf::from("rec/a.mp3"),
+                    parts: ["rec/a.mp3", "rec/a (1).mp3", "rec/a (2).mp3"]
+                        .map(PathBuf::from)
+                        .to_vec()
+                },
+                Recording {
+                    path: PathBuf::from("rec/b.mp3"),
+                    parts: vec![PathBuf::from("rec/b.mp3")]
+                },
+                Recording {
+                    path: PathBuf::from("rec/c (1).mp3"),
+                    parts: vec![PathBuf::from("rec/c (1).mp3")]
+                },
+            ],
+            recordings
+        );
+        let split = recordings.first().unwrap();
+        assert_eq!(PathBuf::from("rec/a.txt"), split.label_path());
+        assert_eq!(
+            PathBuf::from("tmp/a (joined).mp3"),
+            split.joined_path(Path::new("tmp"))
+        );
+        let single = recordings.get(1).unwrap();
+        assert_eq!(
+            PathBuf::from("rec/b.mp3"),
+            single.joined_path(Path::new("tmp"))
+        );
+    }
+
+    #[test]
+    fn combine_crossing_labels() {
+        let label = |start, end, name: &str| TimeLabel::new(secs(start), secs(end), Some(name));
+        let combined = combine_labels([
+            (secs(100), vec![label(10, 40, "a"), label(70, 100, "b")]),
+            (secs(100), vec![label(0, 20, "b"), label(50, 60, "c")]),
+            (secs(100), vec![label(0, 10, "d")]),
+        ]);
+        assert_eq!(
+            vec![
+                (secs(10), secs(40), Some("a")),
+                (secs(70), secs(120), Some("b")),
+                (secs(150), secs(160), Some("c")),
+                (secs(200), secs(210), Some("d")),
+            ],
+            combined
+                .iter()
+                .map(|it| (*it.start(), *it.end(), it.name()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn split_reverses_combine() {
+        let label = |start, end, name: &str| TimeLabel::new(secs(start), secs(end), Some(name));
+        let parts = || {
+            [
+                vec![label(10, 40, "a"), label(70, 100, "b")],
+                vec![label(0, 20, "b"), label(50, 60, "c")],
+                vec![label(0, 10, "d")],
+            ]
+        };
+        let times = |labels: &[TimeLabel]| {
+            labels
+                .iter()
+                .map(|it| (*it.start(), *it.end(), it.name().map(ToOwned::to_owned)))
+                .collect::<Vec<_>>()
+        };
+        let combined = combine_labels(parts().map(|labels| (secs(100), labels)));
+        let split = split_labels([secs(100); 3], &combined);
+        assert_eq!(
+            parts().iter().map(|it| times(it)).collect::<Vec<_>>(),
+            split.iter().map(|it| times(it)).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use super::frame::FrameHeader;
+
+const FLAG_FRAMES: u32 = 0x1;
+const FLAG_BYTES: u32 = 0x2;
+const FLAG_TOC: u32 = 0x4;
+const FLAG_QUALITY: u32 = 0x8;
+const TOC_LEN: usize = 100;
+/// length of the Xing tag with all fields
+const XING_LEN: usize = 4 + 4 + 4 + 4 + TOC_LEN + 4;
+/// length of the LAME extension
+const LAME_LEN: usize = 36;
+/// the biggest value, that fits in the 12 bit fields of the LAME tag
+pub const MAX_GAPLESS: u16 = 0xFFF;
+const ENCODER: &[u8; 9] = b"LAME3.100";
+
+/// the number of samples, that should be skipped at the start and end of a stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Gapless {
+    pub delay: u16,
+    pub padding: u16,
+}
+
+/// reads the encoder delay and padding of a Xing/Info frame with a LAME extension
+#[must_use]
+pub fn read_gapless(frame: &[u8], header: &FrameHeader) -> Option<Gapless> {
+    let start = header.side_info_offset() + header.side_info_len();
+    let tag = frame.get(start..)?;
+    if !(tag.starts_with(b"Xing") || tag.starts_with(b"Info")) {
+        return None;
+    }
+    let flags = u32::from_be_bytes(tag.get(4..8)?.try_into().ok()?);
+    let mut offset = 8;
+    for (flag, len) in [
+        (FLAG_FRAMES, 4),
+        (FLAG_BYTES, 4),
+        (FLAG_TOC, TOC_LEN),
+        (FLAG_QUALITY, 4),
+    ] {
+        if flags & flag != 0 {
+            offset += len;
+        }
+    }
+    let lame = tag.get(offset..offset + LAME_LEN)?;
+    if lame[..9].iter().all(|&b| b == 0) {
+        return None; // no LAME extension
+    }
+    let &[b0, b1, b2] = lame.get(21..24)? else {
+        unreachable!("slice of len 3");
+    };
+    Some(Gapless {
+        delay: (u16::from(b0) << 4) | u16::from(b1 >> 4),
+        padding: (u16::from(b1 & 0x0F) << 8) | u16::from(b2),
+    })
+}
+
+/// the minimal length of a frame, that can hold the Info tag
+#[must_use]
+pub const fn needed_len(header: &FrameHeader) -> usize {
+    FrameHeader::SIZE + header.side_info_len() + XING_LEN + LAME_LEN
+}
+
+/// builds a Xing/Info frame with a LAME extension
+///
+/// # Arguments
+/// `header`: the header bytes of the frame, without crc and big enough to hold the tag (see [`needed_len`])
+///
+/// `frame_lens`: the lengths of all audio frames, following this frame
+///
+/// `is_vbr`: if the audio frames use more than one bitrate
+#[must_use]
+pub fn info_frame(
+    header: [u8; 4],
+    frame_lens: &[usize],
+    is_vbr: bool,
+    gapless: Gapless,
+    music_crc: u16,
+) -> Vec<u8> {
+    let parsed = FrameHeader::parse(&header).expect("need valid header");
+    debug_assert!(!parsed.has_crc, "info frame shouldn't use crc");
+    let frame_len = parsed.frame_len();
+    assert!(
+        frame_len >= needed_len(&parsed),
+        "frame with {frame_len} bytes is to small for info tag"
+    );
+
+    let audio_len = frame_lens.iter().sum::<usize>();
+    let total_len = frame_len + audio_len;
+
+    let mut frame = Vec::with_capacity(frame_len);
+    frame.extend(header);
+    frame.resize(FrameHeader::SIZE + parsed.side_info_len(), 0);
+    frame.extend(if is_vbr { b"Xing" } else { b"Info" });
+    frame.extend((FLAG_FRAMES | FLAG_BYTES | FLAG_TOC | FLAG_QUALITY).to_be_bytes());
+    frame.extend((frame_lens.len() as u32).to_be_bytes());
+    frame.extend((total_len as u32).to_be_bytes());
+    frame.extend(toc(frame_len, frame_lens, total_len));
+    frame.extend(100u32.to_be_bytes()); // quality
+
+    let lame_start = frame.len();
+    frame.extend(ENCODER);
+    frame.push(if is_vbr { 0x04 } else { 0x01 }); // revision 0, vbr method abr / cbr
+    frame.push(0); // lowpass
+    frame.extend([0; 8]); // replay gain
+    frame.push(0); // encoding flags and ath type
+    frame.push(parsed.bitrate.min(255) as u8);
+    let delay = gapless.delay.min(MAX_GAPLESS);
+    let padding = gapless.padding.min(MAX_GAPLESS);
+    frame.extend([
+        (delay >> 4) as u8,
+        (((delay & 0x0F) << 4) | (padding >> 8)) as u8,
+        (padding & 0xFF) as u8,
+    ]);
+    frame.push(0); // misc
+    frame.push(0); // mp3 gain
+    frame.extend([0; 2]); // preset and surround info
+    frame.extend((total_len as u32).to_be_bytes());
+    frame.extend(music_crc.to_be_bytes());
+    let tag_crc = crc16_lame(&frame);
+    frame.extend(tag_crc.to_be_bytes());
+    debug_assert_eq!(lame_start + LAME_LEN, frame.len(), "wrong LAME length");
+
+    frame.resize(frame_len, 0);
+    frame
+}
+
+/// the seek table, mapping each percent of the duration to a fraction of the file size
+fn toc(info_len: usize, frame_lens: &[usize], total_len: usize) -> [u8; TOC_LEN] {
+    let mut toc = [0; TOC_LEN];
+    if frame_lens.is_empty() {
+        return toc;
+    }
+    let mut offsets = Vec::with_capacity(frame_lens.len());
+    let mut offset = info_len;
+    for len in frame_lens {
+        offsets.push(offset);
+        offset += len;
+    }
+    for (i, entry) in toc.iter_mut().enumerate() {
+        let frame = i * frame_lens.len() / TOC_LEN;
+        *entry = (offsets[frame] * 256 / total_len).min(255) as u8;
+    }
+    toc
+}
+
+/// CRC-16 as used by the LAME tag (polynom 0x8005, reflected, init 0)
+#[must_use]
+pub fn crc16_lame(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= u16::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xA001
+            };
+        }
+        crc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splitter::frame::tests::HEADER;
+
+    #[test]
+    fn lame_crc() {
+        // CRC-16/ARC check value
+        assert_eq!(0xBB3D, crc16_lame(b"123456789"));
+    }
+
+    #[test]
+    fn write_and_read_gapless() {
+        let gapless = Gapless {
+            delay: 1105,
+            padding: 2000,
+        };
+        let header = FrameHeader::parse(&HEADER).unwrap();
+        let frame = info_frame(HEADER, &[417; 10], false, gapless, 0);
+        assert_eq!(header.frame_len(), frame.len());
+        assert_eq!(b"Info", &frame[36..40]);
+        assert_eq!(Some(gapless), read_gapless(&frame, &header));
+    }
+
+    #[test]
+    fn seek_table() {
+        let toc = toc(100, &[100; 99], 100 * 100);
+        assert_eq!([2, 2], toc[..2]);
+        assert_eq!(128, toc[50]);
+        assert_eq!(253, toc[99]);
+    }
+}
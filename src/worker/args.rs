@@ -4,20 +4,94 @@ use std::{
     time::Duration,
 };
 
+use super::progress::State;
 use crate::args::{parse_duration, ConfigArgs};
 use clap::Parser;
 use common::args::{debug::OutputLevel, input::Inputs};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BackendKind {
+    /// controls Audacity over mod-script-pipe
+    Audacity,
+    /// edits the label files and cuts mp3 files natively, doesn't need a GUI
+    Files,
+}
+
+/// what happens, when a chapter is moved to a file, that already exists
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Collision {
+    /// keeps the better version of the same episode and both of different episodes
+    #[default]
+    Best,
+    /// keeps the existing file and leaves the new one in the work directory
+    Skip,
+    /// replaces the existing file
+    Overwrite,
+    /// adds a number like " (1)" to the new file
+    Suffix,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum Command {
+    /// loads, names and exports the recordings, same as passing them without a subcommand
+    Run {
+        #[clap(value_name = "FILE", help = "path to audio file")]
+        audio_paths: Vec<PathBuf>,
+    },
+    /// shows how far each recording in a folder got
+    Status {
+        #[clap(
+            value_name = "DIR",
+            help = "folder with the recordings, defaults to the current one"
+        )]
+        dir: Option<PathBuf>,
+    },
+    /// forgets the progress of a recording, so the next run starts over
+    Reset {
+        #[clap(value_name = "FILE", help = "path to audio file")]
+        audio_path: PathBuf,
+        #[clap(
+            long,
+            value_enum,
+            value_name = "STAGE",
+            help = "only go back to this stage, instead of starting over"
+        )]
+        to: Option<State>,
+    },
+    /// forgets the progress of recordings, that no longer exist
+    Clean {
+        #[clap(
+            value_name = "DIR",
+            help = "folder with the recordings, defaults to the current one"
+        )]
+        dir: Option<PathBuf>,
+    },
+}
+
 #[derive(Debug, Parser, Clone)]
 #[clap(version = env!("CARGO_PKG_VERSION"))]
 pub struct Parameter {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     #[clap(value_name = "FILE", help = "path to audio file")]
     pub audio_paths: Vec<PathBuf>,
-    #[clap(long, value_name = "FILE", help = "path to index file")]
+    #[clap(long, global = true, value_name = "FILE", help = "path to index file")]
     pub index_folder: Option<PathBuf>,
 
     #[clap(
         long,
+        global = true,
         value_name = "DURATION",
         help = "timeout, can be just seconds, or somthing like 3h5m17s"
     )]
@@ -26,18 +100,75 @@ pub struct Parameter {
 
     #[clap(
         long,
+        global = true,
         default_value_t = Cow::Borrowed("mp3"),
         value_name = "FORMAT",
-        help = "expected format of exported files"
+        help = "expected format of exported files, only mp3 with the files backend"
     )]
     pub export_ext: Cow<'static, str>,
 
-    #[clap(long, help = "skips loading of data, assumes project is set up")]
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = BackendKind::Audacity,
+        help = "program used to edit the recording"
+    )]
+    pub backend: BackendKind,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "toml file with the names of known labels, only asks for the others"
+    )]
+    pub plan: Option<PathBuf>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "skips loading of data, assumes project is set up"
+    )]
     pub skip_load: bool,
-    #[clap(long, help = "skips naming and exporting of labels")]
+    #[clap(long, global = true, help = "skips naming and exporting of labels")]
     pub skip_name: bool,
 
-    #[clap(long)]
+    #[clap(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "folder for exports and progress, defaults to the folder of each recording"
+    )]
+    pub work_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "folder the chapters are moved into, defaults to the index folder"
+    )]
+    pub output_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        value_name = "TEMPLATE",
+        help = "path of the moved chapters in the output folder, like '{artist}/{series}/{nr:03} - {title}.{ext}'"
+    )]
+    pub output_template: Option<String>,
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        help = "what to do, when a moved chapter already exists"
+    )]
+    pub on_collision: Option<Collision>,
+    #[clap(
+        long,
+        global = true,
+        help = "deletes the joined audio and leftovers of moved chapters, kept chapters stay in the work dir"
+    )]
+    pub cleanup: bool,
+
+    #[clap(long, global = true)]
     pub dry_run: bool,
 
     #[command(flatten)]
@@ -50,15 +181,26 @@ pub struct Parameter {
 
 const SUB_CONFIG: &str = "worker";
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub genre: String,
     pub index_folder: Option<PathBuf>,
+    pub work_dir: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    pub output_template: Option<String>,
+    pub on_collision: Collision,
+    pub cleanup: bool,
 }
 impl Default for Config {
     fn default() -> Self {
         Self {
             genre: "H\u{f6}rbuch".to_owned(),
             index_folder: None,
+            work_dir: None,
+            output_dir: None,
+            output_template: None,
+            on_collision: Collision::default(),
+            cleanup: false,
         }
     }
 }
@@ -106,14 +248,30 @@ impl Arguments {
     }
 
     #[must_use]
-    pub const fn audio_paths(&self) -> &Vec<PathBuf> {
-        &self.parameter.audio_paths
+    pub const fn command(&self) -> Option<&Command> {
+        self.parameter.command.as_ref()
+    }
+    /// the recordings to work on, either passed directly or to the `run` subcommand
+    #[must_use]
+    pub fn audio_paths(&self) -> &[PathBuf] {
+        match &self.parameter.command {
+            Some(Command::Run { audio_paths }) => audio_paths,
+            _ => &self.parameter.audio_paths,
+        }
     }
     #[must_use]
     pub const fn timeout(&self) -> Option<Duration> {
         self.parameter.timeout
     }
     #[must_use]
+    pub const fn backend(&self) -> BackendKind {
+        self.parameter.backend
+    }
+    #[must_use]
+    pub fn plan(&self) -> Option<&Path> {
+        self.parameter.plan.as_deref()
+    }
+    #[must_use]
     pub const fn skip_load(&self) -> bool {
         self.parameter.skip_load
     }
@@ -130,20 +288,55 @@ impl Arguments {
         self.parameter.always_answer
     }
 
-    #[allow(dead_code)]
+    /// the folder for exports and progress of `audio_path`
     #[must_use]
-    pub fn tmp_path(&self) -> &Path {
+    pub fn work_dir<'a>(&'a self, audio_path: &'a Path) -> &'a Path {
+        self.configured_work_dir().unwrap_or_else(|| {
+            audio_path
+                .parent()
+                .filter(|it| !it.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new("."))
+        })
+    }
+    /// the folder for exports and progress, if it doesn't depend on the recording
+    #[must_use]
+    pub fn configured_work_dir(&self) -> Option<&Path> {
+        self.parameter
+            .work_dir
+            .as_deref()
+            .or(self.config.work_dir.as_deref())
+    }
+    /// the folder the chapters are moved into
+    #[must_use]
+    pub fn output_dir(&self) -> Option<&Path> {
+        self.parameter
+            .output_dir
+            .as_deref()
+            .or(self.config.output_dir.as_deref())
+            .or_else(|| self.index_folder())
+    }
+    /// the layout of the output folder, see [`super::template::Template`]
+    #[must_use]
+    pub fn output_template(&self) -> &str {
         self.parameter
-            .audio_paths
-            .first() // TODO find common value
-            .expect("no paths")
-            .parent()
-            .expect("path without parent")
+            .output_template
+            .as_deref()
+            .or(self.config.output_template.as_deref())
+            .unwrap_or(super::template::DEFAULT)
+    }
+    #[must_use]
+    pub fn on_collision(&self) -> Collision {
+        self.parameter
+            .on_collision
+            .unwrap_or(self.config.on_collision)
+    }
+    #[must_use]
+    pub const fn cleanup(&self) -> bool {
+        self.parameter.cleanup || self.config.cleanup
     }
     #[allow(dead_code)]
     fn label_paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
-        self.parameter
-            .audio_paths
+        self.audio_paths()
             .iter()
             .map(|label_path| label_path.with_extension("txt"))
     }
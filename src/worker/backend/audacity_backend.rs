@@ -0,0 +1,323 @@
+use std::{path::Path, time::Duration};
+
+use audacity::{
+    data::{RelativeTo, Save, Selection, TimeLabel, TrackHint},
+    AudacityApi,
+};
+use itertools::Itertools;
+use log::{debug, info, trace};
+
+use super::Backend;
+use crate::{splitter::LabelInfo, worker::Error};
+
+struct LazyApi {
+    timeout: Option<Duration>,
+    cache: Option<AudacityApi>,
+}
+impl LazyApi {
+    const fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            cache: None,
+        }
+    }
+    async fn get_api_handle(&mut self) -> Result<&mut AudacityApi, Error> {
+        let option = &mut self.cache;
+        Ok(match option {
+            Some(x) => x,
+            None => option.insert({
+                audacity::AudacityApiGeneric::launch(None).await?;
+                audacity::AudacityApiGeneric::new(self.timeout).await?
+            }),
+        })
+    }
+}
+
+const fn part(start: Duration, end: Duration) -> Selection {
+    Selection::Part {
+        start,
+        end,
+        relative_to: RelativeTo::ProjectStart,
+    }
+}
+
+/// controls Audacity over mod-script-pipe, launching it when needed
+pub struct Audacity {
+    api: LazyApi,
+}
+impl Audacity {
+    #[must_use]
+    pub const fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            api: LazyApi::new(timeout),
+        }
+    }
+    /// connects to an already running Audacity, that listens on `to_pipe` and answers on `from_pipe`
+    #[cfg(unix)]
+    pub async fn connect(
+        to_pipe: &Path,
+        from_pipe: &Path,
+        timeout: Option<Duration>,
+    ) -> Result<Self, Error> {
+        let options = tokio::net::unix::pipe::OpenOptions::new();
+        let writer = options
+            .open_sender(to_pipe)
+            .map_err(|err| Error::Pipe(to_pipe.to_owned(), err))?;
+        let reader = options
+            .open_receiver(from_pipe)
+            .map_err(|err| Error::Pipe(from_pipe.to_owned(), err))?;
+        let mut api = LazyApi::new(timeout);
+        api.cache = Some(
+            audacity::AudacityApiGeneric::with_pipes(
+                writer,
+                tokio::io::BufReader::new(reader),
+                timeout,
+            )
+            .await?,
+        );
+        Ok(Self { api })
+    }
+}
+
+#[async_trait::async_trait]
+impl Backend for Audacity {
+    async fn load(&mut self, audio_path: &Path, label_path: &Path) -> Result<(), Error> {
+        let audacity = self.api.get_api_handle().await?;
+        trace!("opened audacity");
+        if audacity.get_track_info().await?.is_empty() {
+            trace!("no need to open new project");
+        } else {
+            audacity.write_assume_empty(audacity::command::New).await?;
+            trace!("opened new project");
+        }
+        audacity.import_audio(audio_path).await?;
+        trace!("loaded audio");
+        audacity
+            .import_labels_from(label_path, None::<&str>)
+            .await?;
+        Ok(())
+    }
+
+    async fn labels(&mut self) -> Result<Vec<TimeLabel>, Error> {
+        let labels = self.api.get_api_handle().await?.get_label_info().await?;
+        labels
+            .into_values()
+            .exactly_one()
+            .map_err(|err| Error::LabelTracks(err.len()))
+    }
+    async fn set_label(
+        &mut self,
+        i: usize,
+        name: Option<String>,
+        end: Option<Duration>,
+    ) -> Result<(), Error> {
+        let selected = name.is_some().then_some(false);
+        self.api
+            .get_api_handle()
+            .await?
+            .set_label(i, name, None, end, selected)
+            .await?;
+        Ok(())
+    }
+    async fn add_label(&mut self, label: TimeLabel) -> Result<(), Error> {
+        let _ = self
+            .api
+            .get_api_handle()
+            .await?
+            .add_label(label, Some(TrackHint::LabelTrackNr(0).into()))
+            .await?;
+        Ok(())
+    }
+    async fn remove_label(&mut self, i: usize) -> Result<(), Error> {
+        let mut labels = self.labels().await?;
+        if i >= labels.len() {
+            return Err(Error::NoLabel(i));
+        }
+        let label = labels.swap_remove(i);
+        let api = self.api.get_api_handle().await?;
+        // assumes no overlapping labels in this track
+        api.select(part(*label.start(), *label.end())).await?;
+        api.select_tracks(std::iter::once(1)).await?;
+        api.write_assume_empty(audacity::command::SplitDelete)
+            .await?;
+        Ok(())
+    }
+    async fn replace_labels(
+        &mut self,
+        labels: Vec<TimeLabel>,
+        track_name: &str,
+    ) -> Result<(), Error> {
+        let api = self.api.get_api_handle().await?;
+        api.select_tracks(std::iter::once(1)).await?;
+        api.write_assume_empty(audacity::command::RemoveTracks)
+            .await?;
+        let hint = TrackHint::TrackNr(api.add_label_track(Some(track_name)).await?).into();
+        for label in labels {
+            let _ = api.add_label(label, Some(hint)).await?;
+        }
+        Ok(())
+    }
+    async fn delete_range(&mut self, start: Duration, end: Duration) -> Result<(), Error> {
+        let api = self.api.get_api_handle().await?;
+        api.write_assume_empty(audacity::command::SelAllTracks)
+            .await?;
+        api.select(part(start, end)).await?;
+        api.write_assume_empty(audacity::command::Delete).await?;
+        Ok(())
+    }
+
+    async fn zoom_to(&mut self, start: Duration, end: Duration) -> Result<(), Error> {
+        self.api
+            .get_api_handle()
+            .await?
+            .zoom_to(part(start, end), Save::Discard)
+            .await?;
+        Ok(())
+    }
+    async fn zoom_all(&mut self) -> Result<(), Error> {
+        self.api
+            .get_api_handle()
+            .await?
+            .zoom_to(Selection::All, Save::Discard)
+            .await?;
+        Ok(())
+    }
+    async fn play(&mut self, start: Duration, end: Duration) -> Result<(), Error> {
+        let api = self.api.get_api_handle().await?;
+        api.select(part(start, end)).await?;
+        api.write_assume_empty(audacity::command::Play).await?;
+        Ok(())
+    }
+
+    async fn export_labels(&mut self, path: &Path, dry_run: bool) -> Result<(), Error> {
+        self.api
+            .get_api_handle()
+            .await?
+            .export_all_labels_to(path, dry_run)
+            .await?;
+        Ok(())
+    }
+    async fn export_audio(
+        &mut self,
+        out_dir: &Path,
+        ext: &str,
+        dry_run: bool,
+    ) -> Result<(), Error> {
+        let labels = self.labels().await?;
+        let api = self.api.get_api_handle().await?;
+        for label in &labels {
+            let Some(info) = LabelInfo::from_label(label, ext) else {
+                continue;
+            };
+            let path = out_dir.join(&info.file_name);
+            if dry_run {
+                info!("would export {:?}..{:?} to {}", label.start(), label.end(), path.display());
+                continue;
+            }
+            debug!("exporting {}", path.display());
+            api.write_assume_empty(audacity::command::SelAllTracks)
+                .await?;
+            api.select(part(*label.start(), *label.end())).await?;
+            api.write_assume_empty(audacity::command::Export2 {
+                filename: path,
+                num_channels: 2,
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self, is_last: bool) -> Result<(), Error> {
+        // clear audacity after each round, but exit in last round
+        let api = self.api.get_api_handle().await?;
+        if is_last {
+            api.write_assume_empty(audacity::command::Exit).await?;
+        } else {
+            api.write_assume_empty(audacity::command::Close).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::worker::{
+        backend::mock::MockServer,
+        test_util::{test_dir, write_labels},
+    };
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[tokio::test]
+    async fn load_and_join() {
+        let dir = test_dir("load_and_join");
+        let server = MockServer::start(&dir).unwrap();
+        let label_path = dir.join("labels.txt");
+        write_labels(
+            [
+                TimeLabel::new(secs(10), secs(20), Some("a")),
+                TimeLabel::new(secs(25), secs(40), Some("b")),
+                TimeLabel::new(secs(50), secs(60), Some("c")),
+            ],
+            &label_path,
+        );
+
+        let mut backend = server.connect().await.unwrap();
+        backend
+            .load(Path::new("res/id3test.mp3"), &label_path)
+            .await
+            .unwrap();
+        assert_eq!(3, backend.labels().await.unwrap().len());
+
+        backend.remove_label(1).await.unwrap();
+        backend.set_label(0, None, Some(secs(40))).await.unwrap();
+        backend
+            .set_label(1, Some("d".to_owned()), None)
+            .await
+            .unwrap();
+
+        let project = server.project();
+        assert_eq!(
+            vec![vec![(10.0, 40.0, "a"), (50.0, 60.0, "d")]],
+            project.labels()
+        );
+        assert!(project.command_names().contains(&"SplitDelete"));
+    }
+
+    #[tokio::test]
+    async fn export_each_label() {
+        let dir = test_dir("export_each_label");
+        let server = MockServer::start(&dir).unwrap();
+        let label_path = dir.join("labels.txt");
+        write_labels(
+            [
+                TimeLabel::new(secs(10), secs(20), Some("A 1 X")),
+                TimeLabel::new(secs(25), secs(40), Some("# skipped")),
+                TimeLabel::new(secs(50), secs(60), Some("A 2 Y")),
+            ],
+            &label_path,
+        );
+
+        let mut backend = server.connect().await.unwrap();
+        backend
+            .load(Path::new("res/id3test.mp3"), &label_path)
+            .await
+            .unwrap();
+        backend.export_audio(&dir, "mp3", false).await.unwrap();
+
+        assert_eq!(
+            2,
+            server
+                .project()
+                .command_names()
+                .into_iter()
+                .filter(|it| *it == "Export2")
+                .count()
+        );
+        assert!(dir.join("A 1 X.mp3").exists(), "first label not exported");
+        assert!(dir.join("A 2 Y.mp3").exists(), "second label not exported");
+    }
+}
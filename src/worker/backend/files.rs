@@ -0,0 +1,343 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use audacity::data::TimeLabel;
+use log::{debug, info};
+
+use super::Backend;
+use crate::{
+    splitter::{
+        self,
+        frame::Mp3Stream,
+        lossless::{self, Part},
+        Format, LabelInfo,
+    },
+    worker::Error,
+};
+
+/// works only on the label files and cuts the audio natively, so no GUI is needed
+#[derive(Debug, Default)]
+pub struct Files {
+    project: Option<Project>,
+}
+impl Files {
+    fn project(&mut self) -> Result<&mut Project, Error> {
+        self.project.as_mut().ok_or(Error::NoProject)
+    }
+}
+
+#[derive(Debug)]
+struct Project {
+    audio_path: PathBuf,
+    labels: Vec<TimeLabel>,
+    /// sorted and not overlapping ranges of the recording, that were deleted
+    deleted: Vec<(Duration, Duration)>,
+}
+
+fn with_times(label: &TimeLabel, start: Duration, end: Duration) -> TimeLabel {
+    TimeLabel::new(start, end, label.name())
+}
+
+impl Project {
+    fn new(audio_path: PathBuf, labels: Vec<TimeLabel>) -> Self {
+        Self {
+            audio_path,
+            labels,
+            deleted: Vec::new(),
+        }
+    }
+
+    /// maps `time` of the edited audio to the recording.
+    /// `after` decides, if a time at a deleted range is mapped to its end or start
+    fn to_recording(&self, mut time: Duration, after: bool) -> Duration {
+        for &(start, end) in &self.deleted {
+            if start < time || (after && start == time) {
+                time += end - start;
+            } else {
+                break;
+            }
+        }
+        time
+    }
+
+    /// the ranges of the recording, that make up `start..end` of the edited audio
+    fn segments(&self, start: Duration, end: Duration) -> Vec<(Duration, Duration)> {
+        let mut start = self.to_recording(start, true);
+        let end = self.to_recording(end, false);
+        let mut segments = Vec::new();
+        for &(d_start, d_end) in &self.deleted {
+            if d_start < start || end < d_end {
+                continue;
+            }
+            if start < d_start {
+                segments.push((start, d_start));
+            }
+            start = d_end;
+        }
+        if start < end {
+            segments.push((start, end));
+        }
+        segments
+    }
+
+    fn delete(&mut self, start: Duration, end: Duration) {
+        let range = (self.to_recording(start, true), self.to_recording(end, false));
+        self.deleted.push(range);
+        self.deleted.sort_unstable();
+        self.deleted = self.deleted.iter().fold(Vec::new(), |mut acc, &(start, end)| {
+            match acc.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = end.max(*last_end),
+                _ => acc.push((start, end)),
+            }
+            acc
+        });
+
+        let len = end - start;
+        let shift = |time: Duration| {
+            if time <= start {
+                time
+            } else if time >= end {
+                time - len
+            } else {
+                start
+            }
+        };
+        self.labels = self
+            .labels
+            .iter()
+            .filter(|label| !(start <= *label.start() && *label.end() <= end))
+            .map(|label| with_times(label, shift(*label.start()), shift(*label.end())))
+            .collect();
+    }
+}
+
+#[async_trait::async_trait]
+impl Backend for Files {
+    async fn load(&mut self, audio_path: &Path, label_path: &Path) -> Result<(), Error> {
+        let labels = splitter::read_labels(label_path)?;
+        debug!("read {} labels from {}", labels.len(), label_path.display());
+        self.project = Some(Project::new(audio_path.to_path_buf(), labels));
+        Ok(())
+    }
+    fn keeps_project(&self) -> bool {
+        false
+    }
+
+    async fn labels(&mut self) -> Result<Vec<TimeLabel>, Error> {
+        Ok(self
+            .project()?
+            .labels
+            .iter()
+            .map(|label| with_times(label, *label.start(), *label.end()))
+            .collect())
+    }
+    async fn set_label(
+        &mut self,
+        i: usize,
+        name: Option<String>,
+        end: Option<Duration>,
+    ) -> Result<(), Error> {
+        let label = self.project()?.labels.get_mut(i).ok_or(Error::NoLabel(i))?;
+        *label = TimeLabel::new(
+            *label.start(),
+            end.unwrap_or(*label.end()),
+            name.as_deref().or_else(|| label.name()),
+        );
+        Ok(())
+    }
+    async fn add_label(&mut self, label: TimeLabel) -> Result<(), Error> {
+        let labels = &mut self.project()?.labels;
+        let pos = labels.partition_point(|it| it.start() <= label.start());
+        labels.insert(pos, label);
+        Ok(())
+    }
+    async fn remove_label(&mut self, i: usize) -> Result<(), Error> {
+        let labels = &mut self.project()?.labels;
+        if i >= labels.len() {
+            return Err(Error::NoLabel(i));
+        }
+        labels.remove(i);
+        Ok(())
+    }
+    async fn replace_labels(
+        &mut self,
+        labels: Vec<TimeLabel>,
+        _track_name: &str,
+    ) -> Result<(), Error> {
+        self.project()?.labels = labels;
+        Ok(())
+    }
+    async fn delete_range(&mut self, start: Duration, end: Duration) -> Result<(), Error> {
+        self.project()?.delete(start, end);
+        Ok(())
+    }
+
+    /// plays the parts of the recording with ffplay
+    async fn play(&mut self, start: Duration, end: Duration) -> Result<(), Error> {
+        let project = self.project()?;
+        let audio_path = project.audio_path.clone();
+        for (start, end) in project.segments(start, end) {
+            let map_err = |err: String| Error::Play(audio_path.clone(), err);
+            let output = tokio::process::Command::new("ffplay")
+                .args(["-loglevel", "error", "-nodisp", "-autoexit", "-ss"])
+                .arg(format!("{:.3}", start.as_secs_f64()))
+                .arg("-t")
+                .arg(format!("{:.3}", end.saturating_sub(start).as_secs_f64()))
+                .arg(&audio_path)
+                .output()
+                .await
+                .map_err(|err| map_err(err.to_string()))?;
+            if !output.status.success() {
+                return Err(map_err(
+                    String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    async fn export_labels(&mut self, path: &Path, dry_run: bool) -> Result<(), Error> {
+        let project = self.project()?;
+        TimeLabel::write(
+            project
+                .labels
+                .iter()
+                .map(|label| with_times(label, *label.start(), *label.end())),
+            path,
+            dry_run,
+        )
+        .map_err(|_| Error::WriteLabels(path.to_path_buf()))
+    }
+    async fn export_audio(
+        &mut self,
+        out_dir: &Path,
+        ext: &str,
+        dry_run: bool,
+    ) -> Result<(), Error> {
+        if ext != Format::Mp3.ext() {
+            return Err(Error::UnsupportedFormat(ext.to_owned()));
+        }
+        let project = self.project()?;
+        let stream = Mp3Stream::read(&project.audio_path).map_err(splitter::Error::from)?;
+        for label in &project.labels {
+            let Some(info) = LabelInfo::from_label(label, ext) else {
+                continue;
+            };
+            let path = out_dir.join(&info.file_name);
+            let segments = project.segments(*label.start(), *label.end());
+            if dry_run {
+                info!("would export {segments:?} to {}", path.display());
+                continue;
+            }
+            debug!("exporting {segments:?} to {}", path.display());
+            let data = lossless::join(segments.into_iter().map(|(start, end)| Part {
+                stream: &stream,
+                start,
+                end,
+            }))
+            .map_err(|err| splitter::Error::Cut(path.clone(), err))?;
+            std::fs::write(&path, data).map_err(|err| splitter::Error::Write(path.clone(), err))?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self, _is_last: bool) -> Result<(), Error> {
+        self.project = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+    fn project() -> Project {
+        Project::new(
+            PathBuf::new(),
+            vec![
+                TimeLabel::new(secs(10), secs(20), Some("a")),
+                TimeLabel::new(secs(25), secs(40), Some("b")),
+                TimeLabel::new(secs(50), secs(60), Some("c")),
+            ],
+        )
+    }
+    fn times(project: &Project) -> Vec<(Duration, Duration)> {
+        project
+            .labels
+            .iter()
+            .map(|label| (*label.start(), *label.end()))
+            .collect()
+    }
+
+    #[test]
+    fn delete_moves_labels() {
+        let mut project = project();
+        project.delete(secs(20), secs(25));
+        assert_eq!(
+            vec![(secs(10), secs(20)), (secs(20), secs(35)), (secs(45), secs(55))],
+            times(&project)
+        );
+        project.delete(secs(35), secs(45));
+        assert_eq!(
+            vec![(secs(10), secs(20)), (secs(20), secs(35)), (secs(35), secs(45))],
+            times(&project)
+        );
+        assert_eq!(vec![(secs(20), secs(25)), (secs(40), secs(50))], project.deleted);
+    }
+
+    #[test]
+    fn delete_removes_inner_labels() {
+        let mut project = project();
+        project.delete(secs(21), secs(45));
+        assert_eq!(
+            vec![(secs(10), secs(20)), (secs(26), secs(36))],
+            times(&project)
+        );
+    }
+
+    #[test]
+    fn segments_skip_deleted() {
+        let mut project = project();
+        project.delete(secs(40), secs(50));
+        project.delete(secs(20), secs(25));
+        assert_eq!(vec![(secs(20), secs(25)), (secs(40), secs(50))], project.deleted);
+        assert_eq!(
+            vec![(secs(10), secs(20)), (secs(25), secs(40)), (secs(50), secs(60))],
+            project.segments(secs(10), secs(45))
+        );
+        assert_eq!(vec![(secs(50), secs(60))], project.segments(secs(35), secs(45)));
+    }
+
+    #[tokio::test]
+    async fn bad_index_fails() {
+        let mut files = Files {
+            project: Some(project()),
+        };
+        assert!(
+            matches!(
+                files.set_label(3, Some("d".to_owned()), None).await,
+                Err(Error::NoLabel(3))
+            ),
+            "renamed a missing label"
+        );
+        assert!(
+            matches!(files.remove_label(3).await, Err(Error::NoLabel(3))),
+            "removed a missing label"
+        );
+        assert_eq!(3, files.labels().await.unwrap().len());
+    }
+
+    #[test]
+    fn merge_deleted_ranges() {
+        let mut project = project();
+        project.delete(secs(20), secs(25));
+        project.delete(secs(15), secs(20));
+        assert_eq!(vec![(secs(15), secs(25))], project.deleted);
+        assert_eq!(vec![(secs(25), secs(40))], project.segments(secs(15), secs(30)));
+    }
+}
@@ -0,0 +1,515 @@
+//! a fake Audacity, that answers mod-script-pipe commands from an in-memory project
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use audacity::data::TimeLabel;
+use itertools::Itertools;
+
+use super::Audacity;
+use crate::{splitter::frame::Mp3Stream, worker::Error};
+
+const TIMEOUT: Option<Duration> = Some(Duration::from_secs(5));
+const QUIT: &str = "MockQuit:";
+const OK: &str = "BatchCommand finished: OK\n\n";
+const FAILED: &str = "BatchCommand finished: Failed!\n\n";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Wave { end: f64 },
+    Label(Vec<Label>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub name: String,
+    pub kind: Kind,
+    pub selected: bool,
+}
+
+/// the state of the fake Audacity
+#[derive(Debug, Default)]
+pub struct MockProject {
+    pub tracks: Vec<Track>,
+    pub selection: (f64, f64),
+    /// all received commands in order
+    pub commands: Vec<String>,
+}
+
+/// splits `Name: Key=Value Key="quoted value"` into the name and the parameters
+fn parse_command(line: &str) -> Option<(&str, HashMap<String, String>)> {
+    let (name, params) = line.split_once(':')?;
+    let params = shellwords::split(params)
+        .ok()?
+        .into_iter()
+        .filter_map(|param| {
+            param
+                .split_once('=')
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        })
+        .collect();
+    Some((name.trim(), params))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl MockProject {
+    /// the names of all received commands
+    pub fn command_names(&self) -> Vec<&str> {
+        self.commands
+            .iter()
+            .map(|it| it.split_once(':').map_or(it.as_str(), |(name, _)| name))
+            .collect()
+    }
+    /// the parameters of all received `name` commands
+    pub fn params(&self, name: &str) -> Vec<HashMap<String, String>> {
+        self.commands
+            .iter()
+            .filter_map(|it| parse_command(it))
+            .filter(|(it, _)| *it == name)
+            .map(|(_, params)| params)
+            .collect()
+    }
+    /// the labels of all label tracks as `(start, end, text)`
+    pub fn labels(&self) -> Vec<Vec<(f64, f64, &str)>> {
+        self.tracks
+            .iter()
+            .filter_map(|track| match &track.kind {
+                Kind::Label(labels) => Some(
+                    labels
+                        .iter()
+                        .map(|label| (label.start, label.end, label.text.as_str()))
+                        .collect(),
+                ),
+                Kind::Wave { .. } => None,
+            })
+            .collect()
+    }
+
+    /// runs the command in `line` and returns the response
+    pub fn execute(&mut self, line: &str) -> String {
+        self.commands.push(line.to_owned());
+        let Some((name, params)) = parse_command(line) else {
+            return format!("Syntax error in {line:?}\n{FAILED}");
+        };
+        let param = |key: &str| params.get(key).map(String::as_str);
+        let time = |key: &str| param(key).and_then(|it| it.parse::<f64>().ok());
+        let index = |key: &str| param(key).and_then(|it| it.parse::<usize>().ok());
+
+        let response = match name {
+            "GetInfo" => match param("Type") {
+                Some("Tracks") => Ok(self.tracks_json()),
+                Some("Labels") => Ok(self.labels_json()),
+                _ => Err(format!("unsupported type {:?}", param("Type"))),
+            },
+            "New" | "Close" | "Exit" => {
+                self.tracks.clear();
+                Ok(String::new())
+            }
+            "Import2" => param("Filename")
+                .ok_or_else(|| "missing Filename".to_owned())
+                .and_then(|path| self.import(Path::new(path))),
+            "Export2" => param("Filename")
+                .ok_or_else(|| "missing Filename".to_owned())
+                .and_then(|path| std::fs::write(path, b"").map_err(|err| err.to_string())),
+            "NewLabelTrack" => {
+                self.add_label_track("Label");
+                Ok(String::new())
+            }
+            "SetTrack" => match (index("Track"), param("Name")) {
+                (Some(i), Some(name)) if i < self.tracks.len() => {
+                    self.tracks[i].name = name.to_owned();
+                    Ok(String::new())
+                }
+                _ => Err("invalid track".to_owned()),
+            },
+            "Select" | "SelectTime" | "SelectTracks" => {
+                if let (Some(start), Some(end)) = (time("Start"), time("End")) {
+                    self.selection = (start, end);
+                }
+                if let Some(first) = index("Track") {
+                    let count = index("TrackCount").unwrap_or(1);
+                    let mode = param("Mode").unwrap_or("Set");
+                    for (i, track) in self.tracks.iter_mut().enumerate() {
+                        let in_range = (first..first + count).contains(&i);
+                        track.selected = match mode {
+                            "Add" => track.selected || in_range,
+                            "Remove" => track.selected && !in_range,
+                            _ => in_range,
+                        };
+                    }
+                }
+                Ok(String::new())
+            }
+            "SelAllTracks" => {
+                self.tracks.iter_mut().for_each(|track| track.selected = true);
+                Ok(String::new())
+            }
+            "SelectAll" => {
+                self.tracks.iter_mut().for_each(|track| track.selected = true);
+                self.selection = (0.0, self.end());
+                Ok(String::new())
+            }
+            "AddLabel" => {
+                let (start, end) = self.selection;
+                if self.selected_label_tracks().next().is_none() {
+                    self.add_label_track("Label");
+                }
+                let labels = self
+                    .selected_label_tracks()
+                    .next()
+                    .expect("just added a label track");
+                labels.push(Label {
+                    start,
+                    end,
+                    text: String::new(),
+                });
+                labels.sort_by(|a, b| a.start.total_cmp(&b.start));
+                Ok(String::new())
+            }
+            "SetLabel" => match index("Label").and_then(|i| self.labels_mut().nth(i)) {
+                Some(label) => {
+                    if let Some(text) = param("Text") {
+                        text.clone_into(&mut label.text);
+                    }
+                    label.start = time("Start").unwrap_or(label.start);
+                    label.end = time("End").unwrap_or(label.end);
+                    Ok(String::new())
+                }
+                None => Err("invalid label".to_owned()),
+            },
+            "SplitDelete" => {
+                self.delete(false);
+                Ok(String::new())
+            }
+            "Delete" => {
+                self.delete(true);
+                Ok(String::new())
+            }
+            "RemoveTracks" => {
+                self.tracks.retain(|track| !track.selected);
+                Ok(String::new())
+            }
+            // view only commands
+            _ => Ok(String::new()),
+        };
+        match response {
+            Ok(response) => format!("{response}{OK}"),
+            Err(err) => format!("{err}\n{FAILED}"),
+        }
+    }
+
+    fn end(&self) -> f64 {
+        self.tracks
+            .iter()
+            .filter_map(|track| match track.kind {
+                Kind::Wave { end } => Some(end),
+                Kind::Label(_) => None,
+            })
+            .fold(0.0, f64::max)
+    }
+    fn add_label_track(&mut self, name: &str) {
+        self.tracks.iter_mut().for_each(|track| track.selected = false);
+        self.tracks.push(Track {
+            name: name.to_owned(),
+            kind: Kind::Label(Vec::new()),
+            selected: true,
+        });
+    }
+    fn selected_label_tracks(&mut self) -> impl Iterator<Item = &mut Vec<Label>> {
+        self.tracks
+            .iter_mut()
+            .filter(|track| track.selected)
+            .filter_map(|track| match &mut track.kind {
+                Kind::Label(labels) => Some(labels),
+                Kind::Wave { .. } => None,
+            })
+    }
+    fn labels_mut(&mut self) -> impl Iterator<Item = &mut Label> {
+        self.tracks
+            .iter_mut()
+            .filter_map(|track| match &mut track.kind {
+                Kind::Label(labels) => Some(labels),
+                Kind::Wave { .. } => None,
+            })
+            .flatten()
+    }
+
+    fn import(&mut self, path: &Path) -> Result<String, String> {
+        let name = path
+            .file_stem()
+            .map_or_else(String::new, |it| it.to_string_lossy().into_owned());
+        let kind = if path.extension().is_some_and(|ext| ext == "txt") {
+            let labels = TimeLabel::read(path).map_err(|_| format!("couldn't read {path:?}"))?;
+            Kind::Label(
+                labels
+                    .into_iter()
+                    .map(|label| Label {
+                        start: label.start().as_secs_f64(),
+                        end: label.end().as_secs_f64(),
+                        text: label.name().unwrap_or_default().to_owned(),
+                    })
+                    .collect(),
+            )
+        } else {
+            let stream = Mp3Stream::read(path).map_err(|err| err.to_string())?;
+            Kind::Wave {
+                end: stream.duration().as_secs_f64(),
+            }
+        };
+        self.tracks.push(Track {
+            name,
+            kind,
+            selected: false,
+        });
+        Ok(String::new())
+    }
+
+    /// deletes the selection of all selected tracks, when `shift` is set the rest is moved to the start
+    fn delete(&mut self, shift: bool) {
+        let (start, end) = self.selection;
+        let len = end - start;
+        let move_time = |time: f64| {
+            if !shift || time <= start {
+                time
+            } else if time >= end {
+                time - len
+            } else {
+                start
+            }
+        };
+        for track in self.tracks.iter_mut().filter(|track| track.selected) {
+            match &mut track.kind {
+                Kind::Wave { end: wave_end } => {
+                    if shift {
+                        *wave_end = move_time(*wave_end);
+                    }
+                }
+                Kind::Label(labels) => {
+                    labels.retain(|label| !(start <= label.start && label.end <= end));
+                    for label in labels {
+                        label.start = move_time(label.start);
+                        label.end = move_time(label.end);
+                    }
+                }
+            }
+        }
+    }
+
+    fn tracks_json(&self) -> String {
+        let tracks = self
+            .tracks
+            .iter()
+            .map(|track| {
+                let common = format!(
+                    "\"name\":\"{}\", \"focused\":0, \"selected\":{}",
+                    escape(&track.name),
+                    u8::from(track.selected)
+                );
+                match track.kind {
+                    Kind::Wave { end } => format!(
+                        "{{ {common}, \"kind\":\"wave\", \"start\":0, \"end\":{end}, \"pan\":0, \"gain\":1, \"channels\":2, \"solo\":0, \"mute\":0, \"VZoomMin\":-1, \"VZoomMax\":1 }}"
+                    ),
+                    Kind::Label(_) => format!("{{ {common}, \"kind\":\"label\" }}"),
+                }
+            })
+            .join(",\n");
+        format!("[\n{tracks}\n]\n")
+    }
+    fn labels_json(&self) -> String {
+        let tracks = self
+            .tracks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, track)| match &track.kind {
+                Kind::Label(labels) => Some((i, labels)),
+                Kind::Wave { .. } => None,
+            })
+            .map(|(i, labels)| {
+                let labels = labels
+                    .iter()
+                    .map(|label| {
+                        format!(
+                            "[{}, {}, \"{}\"]",
+                            label.start,
+                            label.end,
+                            escape(&label.text)
+                        )
+                    })
+                    .join(",\n");
+                format!("[ {i},\n[\n{labels}\n]\n]")
+            })
+            .join(",\n");
+        format!("[\n{tracks}\n]\n")
+    }
+}
+
+/// serves a [`MockProject`] on its own pipes, so tests can run in parallel next to a real Audacity
+pub struct MockServer {
+    project: Arc<Mutex<MockProject>>,
+    to_pipe: PathBuf,
+    from_pipe: PathBuf,
+    waker: File,
+}
+impl MockServer {
+    /// creates the pipes in `dir` and answers the commands sent to them
+    pub fn start(dir: &Path) -> std::io::Result<Self> {
+        let [to_pipe, from_pipe] =
+            ["to", "from"].map(|it| dir.join(format!("audacity_script_pipe.{it}")));
+        for pipe in [&to_pipe, &from_pipe] {
+            if !pipe.exists() {
+                let status = std::process::Command::new("mkfifo").arg(pipe).status()?;
+                if !status.success() {
+                    return Err(std::io::Error::other(format!("couldn't create {pipe:?}")));
+                }
+            }
+        }
+        // opening a fifo for reading and writing doesn't block until the other side connects
+        let open = |path: &Path| OpenOptions::new().read(true).write(true).open(path);
+        let reader = open(&to_pipe)?;
+        let waker = reader.try_clone()?;
+        let mut writer = open(&from_pipe)?;
+
+        let project = Arc::<Mutex<MockProject>>::default();
+        let server_project = Arc::clone(&project);
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else { break };
+                if line == QUIT {
+                    break;
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let response = server_project
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .execute(&line);
+                if writer
+                    .write_all(response.as_bytes())
+                    .and_then(|()| writer.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            project,
+            to_pipe,
+            from_pipe,
+            waker,
+        })
+    }
+
+    /// an [`Audacity`] backend talking to this server
+    pub async fn connect(&self) -> Result<Audacity, Error> {
+        Audacity::connect(&self.to_pipe, &self.from_pipe, TIMEOUT).await
+    }
+
+    pub fn project(&self) -> MutexGuard<'_, MockProject> {
+        self.project.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        let _ = writeln!(self.waker, "{QUIT}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> MockProject {
+        let mut project = MockProject {
+            tracks: vec![
+                Track {
+                    name: "audio".to_owned(),
+                    kind: Kind::Wave { end: 100.0 },
+                    selected: false,
+                },
+                Track {
+                    name: "audio".to_owned(),
+                    kind: Kind::Label(Vec::new()),
+                    selected: false,
+                },
+            ],
+            ..Default::default()
+        };
+        for (start, end) in [(10, 20), (25, 40), (50, 60)] {
+            project.execute(&format!("Select: Start={start} End={end} Track=1"));
+            project.execute("AddLabel:");
+        }
+        project
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Some((
+                "SetLabel",
+                HashMap::from([
+                    ("Label".to_owned(), "2".to_owned()),
+                    ("Text".to_owned(), "Die drei ??? 5".to_owned())
+                ])
+            )),
+            parse_command("SetLabel: Label=2 Text=\"Die drei ??? 5\"")
+        );
+        assert_eq!(None, parse_command("no command"));
+    }
+
+    #[test]
+    fn set_label() {
+        let mut project = project();
+        assert_eq!(OK, project.execute("SetLabel: Label=1 Text=\"b \\\"x\\\"\" End=45"));
+        assert_eq!(
+            vec![vec![(10.0, 20.0, ""), (25.0, 45.0, "b \"x\""), (50.0, 60.0, "")]],
+            project.labels()
+        );
+        assert!(project.execute("SetLabel: Label=3").ends_with(FAILED));
+    }
+
+    #[test]
+    fn delete_moves_labels() {
+        let mut project = project();
+        project.execute("SelAllTracks:");
+        project.execute("Select: Start=40 End=50");
+        project.execute("Delete:");
+        assert_eq!(
+            vec![vec![(10.0, 20.0, ""), (25.0, 40.0, ""), (40.0, 50.0, "")]],
+            project.labels()
+        );
+        assert_eq!(Kind::Wave { end: 90.0 }, project.tracks[0].kind);
+
+        project.execute("Select: Start=25 End=40 Track=1");
+        project.execute("SplitDelete:");
+        assert_eq!(
+            vec![vec![(10.0, 20.0, ""), (40.0, 50.0, "")]],
+            project.labels()
+        );
+    }
+
+    #[test]
+    fn labels_json() {
+        let mut project = project();
+        project.execute("SetLabel: Label=0 Text=a");
+        assert!(project
+            .execute("GetInfo: Type=Labels Format=JSON")
+            .starts_with("[\n[ 1,\n[\n[10, 20, \"a\"],\n"));
+    }
+}
@@ -0,0 +1,70 @@
+use std::{path::Path, time::Duration};
+
+use audacity::data::TimeLabel;
+
+use super::Error;
+
+mod audacity_backend;
+mod files;
+#[cfg(all(test, unix))]
+pub(crate) mod mock;
+
+pub use audacity_backend::Audacity;
+pub use files::Files;
+
+/// the operations of an audio editor, the worker needs to name, merge and export the labels of a recording
+///
+/// All label indices refer to the order of [`Backend::labels`].
+#[async_trait::async_trait]
+pub trait Backend: Send {
+    /// opens a new project with the audio of `audio_path` and the labels of `label_path`
+    async fn load(&mut self, audio_path: &Path, label_path: &Path) -> Result<(), Error>;
+    /// if the project survives the end of the program, so loading can be skipped
+    fn keeps_project(&self) -> bool {
+        true
+    }
+
+    /// the labels of the only label track
+    async fn labels(&mut self) -> Result<Vec<TimeLabel>, Error>;
+    /// changes the name and/or end of the `i`-th label
+    async fn set_label(
+        &mut self,
+        i: usize,
+        name: Option<String>,
+        end: Option<Duration>,
+    ) -> Result<(), Error>;
+    /// inserts `label` into the label track, keeping the labels sorted by their start
+    async fn add_label(&mut self, label: TimeLabel) -> Result<(), Error>;
+    /// removes the `i`-th label, without changing the audio
+    async fn remove_label(&mut self, i: usize) -> Result<(), Error>;
+    /// replaces the label track with a new track called `track_name` containing `labels`
+    async fn replace_labels(
+        &mut self,
+        labels: Vec<TimeLabel>,
+        track_name: &str,
+    ) -> Result<(), Error>;
+    /// deletes `start..end` of all tracks, moving everything after it
+    async fn delete_range(&mut self, start: Duration, end: Duration) -> Result<(), Error>;
+
+    /// shows `start..end` to the user
+    async fn zoom_to(&mut self, _start: Duration, _end: Duration) -> Result<(), Error> {
+        Ok(())
+    }
+    /// shows the whole project to the user
+    async fn zoom_all(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+    /// plays `start..end` to the user
+    async fn play(&mut self, _start: Duration, _end: Duration) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// writes the labels into `path`
+    async fn export_labels(&mut self, path: &Path, dry_run: bool) -> Result<(), Error>;
+    /// writes the audio of each label into `out_dir` with the extension `ext`
+    async fn export_audio(&mut self, out_dir: &Path, ext: &str, dry_run: bool)
+        -> Result<(), Error>;
+
+    /// closes the project, `is_last` signals, that no other project will be opened
+    async fn close(&mut self, is_last: bool) -> Result<(), Error>;
+}
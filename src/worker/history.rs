@@ -0,0 +1,322 @@
+use std::time::Duration;
+
+use audacity::data::TimeLabel;
+
+use super::{backend::Backend, Error};
+
+/// an owned copy of a label, so it can be restored later
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelData {
+    pub start: Duration,
+    pub end: Duration,
+    pub name: Option<String>,
+}
+impl From<&TimeLabel> for LabelData {
+    fn from(value: &TimeLabel) -> Self {
+        Self {
+            start: *value.start(),
+            end: *value.end(),
+            name: value.name().map(ToOwned::to_owned),
+        }
+    }
+}
+impl LabelData {
+    #[must_use]
+    pub fn to_label(&self) -> TimeLabel {
+        TimeLabel::new(self.start, self.end, self.name.as_deref())
+    }
+}
+
+/// a change of the labels, that can be reverted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// the `i`-th label was renamed from `old` to `new`
+    Rename {
+        i: usize,
+        old: Option<String>,
+        new: String,
+    },
+    /// the `i`-th label was removed and the previous label extended to its end
+    Join {
+        i: usize,
+        removed: LabelData,
+        old_end: Duration,
+    },
+    /// the `i`-th label was removed
+    Delete { i: usize, removed: LabelData },
+    /// `label` was inserted as the `i`-th label
+    Insert { i: usize, label: LabelData },
+    /// the end of the `i`-th label was moved
+    Resize {
+        i: usize,
+        old_end: Duration,
+        new_end: Duration,
+    },
+    /// multiple edits, that are applied and reverted together
+    Batch(Vec<Edit>),
+}
+impl Edit {
+    pub async fn apply(&self, backend: &mut dyn Backend) -> Result<(), Error> {
+        match self {
+            Self::Batch(edits) => {
+                for edit in edits {
+                    edit.apply_single(backend).await?;
+                }
+                Ok(())
+            }
+            edit => edit.apply_single(backend).await,
+        }
+    }
+    pub async fn revert(&self, backend: &mut dyn Backend) -> Result<(), Error> {
+        match self {
+            Self::Batch(edits) => {
+                for edit in edits.iter().rev() {
+                    edit.revert_single(backend).await?;
+                }
+                Ok(())
+            }
+            edit => edit.revert_single(backend).await,
+        }
+    }
+
+    async fn apply_single(&self, backend: &mut dyn Backend) -> Result<(), Error> {
+        match self {
+            Self::Rename { i, new, .. } => backend.set_label(*i, Some(new.clone()), None).await,
+            Self::Join { i, removed, .. } => {
+                backend.remove_label(*i).await?;
+                backend.set_label(*i - 1, None, Some(removed.end)).await
+            }
+            Self::Delete { i, .. } => backend.remove_label(*i).await,
+            Self::Insert { label, .. } => backend.add_label(label.to_label()).await,
+            Self::Resize { i, new_end, .. } => backend.set_label(*i, None, Some(*new_end)).await,
+            Self::Batch(_) => unreachable!("batches can't be nested"),
+        }
+    }
+    async fn revert_single(&self, backend: &mut dyn Backend) -> Result<(), Error> {
+        match self {
+            Self::Rename {
+                i, old: Some(old), ..
+            } => backend.set_label(*i, Some(old.clone()), None).await,
+            Self::Rename { i, old: None, .. } => {
+                // a name can't be removed, so the label is added again without one
+                let label = backend
+                    .labels()
+                    .await?
+                    .get(*i)
+                    .map(|it| TimeLabel::new::<&str>(*it.start(), *it.end(), None))
+                    .ok_or(Error::NoLabel(*i))?;
+                backend.remove_label(*i).await?;
+                backend.add_label(label).await
+            }
+            Self::Join {
+                i,
+                removed,
+                old_end,
+            } => {
+                backend.set_label(*i - 1, None, Some(*old_end)).await?;
+                backend.add_label(removed.to_label()).await
+            }
+            Self::Delete { removed, .. } => backend.add_label(removed.to_label()).await,
+            Self::Insert { i, .. } => backend.remove_label(*i).await,
+            Self::Resize { i, old_end, .. } => backend.set_label(*i, None, Some(*old_end)).await,
+            Self::Batch(_) => unreachable!("batches can't be nested"),
+        }
+    }
+}
+
+/// the applied and reverted [`Edit`]s, each stored with the state `S` of the editor before it
+#[derive(Debug)]
+pub struct History<S> {
+    done: Vec<(Edit, S)>,
+    undone: Vec<(Edit, S)>,
+}
+impl<S> Default for History<S> {
+    fn default() -> Self {
+        Self {
+            done: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+}
+impl<S: Send> History<S> {
+    /// remembers the already applied `edit`, this forgets all reverted edits
+    pub fn record(&mut self, edit: Edit, before: S) {
+        self.done.push((edit, before));
+        self.undone.clear();
+    }
+
+    /// reverts the last edit, returns the state from before it or `None` if there is nothing to undo
+    pub async fn undo(
+        &mut self,
+        backend: &mut dyn Backend,
+        current: S,
+    ) -> Result<Option<S>, Error> {
+        let Some((edit, before)) = self.done.pop() else {
+            return Ok(None);
+        };
+        log::debug!("undo {edit:?}");
+        edit.revert(backend).await?;
+        self.undone.push((edit, current));
+        Ok(Some(before))
+    }
+    /// applies the last reverted edit, returns the state from after it or `None` if there is nothing to redo
+    pub async fn redo(
+        &mut self,
+        backend: &mut dyn Backend,
+        current: S,
+    ) -> Result<Option<S>, Error> {
+        let Some((edit, after)) = self.undone.pop() else {
+            return Ok(None);
+        };
+        log::debug!("redo {edit:?}");
+        edit.apply(backend).await?;
+        self.done.push((edit, current));
+        Ok(Some(after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::worker::{
+        backend::Files,
+        test_util::{files_backend, test_dir},
+    };
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    async fn backend(name: &str) -> Files {
+        files_backend(
+            &test_dir(name),
+            [
+                TimeLabel::new(secs(0), secs(10), Some("a")),
+                TimeLabel::new(secs(15), secs(20), Some("b")),
+                TimeLabel::new(secs(25), secs(30), Some("c")),
+            ],
+        )
+        .await
+    }
+    async fn labels(backend: &mut Files) -> Vec<LabelData> {
+        backend
+            .labels()
+            .await
+            .unwrap()
+            .iter()
+            .map(LabelData::from)
+            .collect_vec()
+    }
+
+    #[tokio::test]
+    async fn undo_redo() {
+        let mut backend = backend("undo_redo").await;
+        let original = labels(&mut backend).await;
+        assert_eq!(3, original.len(), "wrong test data");
+        let mut history = History::default();
+
+        let edits = [
+            Edit::Rename {
+                i: 0,
+                old: Some("a".to_owned()),
+                new: "x".to_owned(),
+            },
+            Edit::Join {
+                i: 1,
+                removed: original[1].clone(),
+                old_end: secs(10),
+            },
+            Edit::Delete {
+                i: 1,
+                removed: original[2].clone(),
+            },
+        ];
+        for (state, edit) in edits.into_iter().enumerate() {
+            edit.apply(&mut backend).await.unwrap();
+            history.record(edit, state);
+        }
+        let edited = labels(&mut backend).await;
+        assert_eq!(
+            vec![LabelData {
+                start: secs(0),
+                end: secs(20),
+                name: Some("x".to_owned())
+            }],
+            edited
+        );
+
+        for expected in [2, 1, 0] {
+            assert_eq!(
+                Some(expected),
+                history.undo(&mut backend, 10).await.unwrap()
+            );
+        }
+        assert_eq!(None, history.undo(&mut backend, 10).await.unwrap());
+        assert_eq!(original, labels(&mut backend).await);
+
+        for _ in 0..3 {
+            assert_eq!(Some(10), history.redo(&mut backend, 0).await.unwrap());
+        }
+        assert_eq!(None, history.redo(&mut backend, 0).await.unwrap());
+        assert_eq!(edited, labels(&mut backend).await);
+    }
+
+    #[tokio::test]
+    async fn undo_rename_of_unnamed() {
+        let mut backend = files_backend(
+            &test_dir("undo_rename_of_unnamed"),
+            [TimeLabel::new::<&str>(secs(0), secs(10), None)],
+        )
+        .await;
+        let original = labels(&mut backend).await;
+        let rename = Edit::Rename {
+            i: 0,
+            old: None,
+            new: "x".to_owned(),
+        };
+        rename.apply(&mut backend).await.unwrap();
+        rename.revert(&mut backend).await.unwrap();
+        assert_eq!(original, labels(&mut backend).await);
+    }
+
+    #[tokio::test]
+    async fn batch() {
+        let mut backend = backend("batch").await;
+        let original = labels(&mut backend).await;
+        let split = Edit::Batch(vec![
+            Edit::Resize {
+                i: 0,
+                old_end: secs(10),
+                new_end: secs(5),
+            },
+            Edit::Insert {
+                i: 1,
+                label: LabelData {
+                    start: secs(5),
+                    end: secs(10),
+                    name: Some("a2".to_owned()),
+                },
+            },
+            Edit::Rename {
+                i: 2,
+                old: Some("b".to_owned()),
+                new: "b2".to_owned(),
+            },
+        ]);
+        split.apply(&mut backend).await.unwrap();
+        assert_eq!(
+            vec!["a", "a2", "b2", "c"],
+            backend
+                .labels()
+                .await
+                .unwrap()
+                .iter()
+                .map(|label| label.name().unwrap().to_owned())
+                .collect_vec()
+        );
+        split.revert(&mut backend).await.unwrap();
+        assert_eq!(original, labels(&mut backend).await);
+    }
+}
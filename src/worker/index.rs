@@ -1,3 +1,4 @@
+use chrono::{Datelike, NaiveDate};
 use itertools::Itertools;
 use log::warn;
 use regex::Regex;
@@ -138,6 +139,22 @@ pub enum DateOrYear {
     Date(Datetime),
     Year(u16),
 }
+impl DateOrYear {
+    /// if the release was definitely after `date`, so it can't be in a recording from that day
+    #[must_use]
+    pub fn is_after(self, date: NaiveDate) -> bool {
+        match self {
+            Self::Year(year) => i32::from(year) > date.year(),
+            Self::Date(datetime) => datetime.date.is_some_and(|release| {
+                (
+                    i32::from(release.year),
+                    u32::from(release.month),
+                    u32::from(release.day),
+                ) > (date.year(), date.month(), date.day())
+            }),
+        }
+    }
+}
 
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
@@ -380,13 +397,16 @@ impl<'a> Index<'a> {
 
 #[allow(clippy::module_name_repetitions)]
 pub struct MultiIndex<'a> {
-    folder: PathBuf,
+    folder: Option<PathBuf>,
+    /// index files added with [`MultiIndex::add_index_file`], so they survive a reload
+    added: Vec<PathBuf>,
     data: HashMap<OsString, Index<'a>>,
 }
 impl<'i> Debug for MultiIndex<'i> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("MultiIndex")
             .field("folder", &self.folder)
+            .field("added", &self.added)
             .field("data", &self.data.keys())
             .finish()
     }
@@ -395,7 +415,20 @@ impl MultiIndex<'static> {
     #[must_use]
     pub async fn new(folder: PathBuf) -> Self {
         let data = Self::possible(&folder).await;
-        Self { folder, data }
+        Self {
+            folder: Some(folder),
+            added: Vec::new(),
+            data,
+        }
+    }
+    /// an index without a folder, that only knows the index files added later
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            folder: None,
+            added: Vec::new(),
+            data: HashMap::new(),
+        }
     }
 }
 
@@ -411,59 +444,85 @@ impl<'a> MultiIndex<'a> {
 
         for path in paths {
             let path = path.unwrap();
-            let with_extension = path.with_extension("");
-            let name = with_extension
-                .file_name()
-                .filter(|&it| {
-                    let it = it.to_string_lossy();
-                    it != "index" && it != "index_full"
-                })
-                .or_else(|| path.parent().unwrap().file_name())
-                .expect("need filename")
-                .to_owned();
             match Index::try_read_from_path(&path).await {
-                Ok(index) => match index.part {
-                    IndexPart::SubSeries { subseries } => {
-                        for sub in subseries {
-                            let mut name = name.clone();
-                            name.push(Self::SUBSERIES_DELIMENITER);
-                            name.push(sub.name.as_ref());
-
-                            known.insert(
-                                name,
-                                Index {
-                                    url: index.url.clone(),
-                                    artist: index.artist.clone(),
-                                    release: index.release,
-                                    part: IndexPart::Direct {
-                                        chapters: Chapters {
-                                            main: sub.chapters,
-                                            extra: Vec::new(),
-                                        },
-                                    },
-                                },
-                            );
-                        }
-                    }
-                    IndexPart::Direct { chapters: _ } => {
-                        known.insert(name, index);
-                    }
-                },
+                Ok(index) => Self::insert(&mut known, Self::series_name(&path), index),
                 Err(err) => warn!("failed to open index at {} because {err}", path.display()),
             }
         }
 
         known
     }
+    /// the name of the series, the index file at `path` belongs to
+    fn series_name(path: &Path) -> OsString {
+        let with_extension = path.with_extension("");
+        with_extension
+            .file_name()
+            .filter(|&it| {
+                let it = it.to_string_lossy();
+                it != "index" && it != "index_full"
+            })
+            .or_else(|| path.parent().unwrap().file_name())
+            .expect("need filename")
+            .to_owned()
+    }
+    /// inserts `index` as `name`, or each subseries of it as "`name`: `subseries`"
+    fn insert(known: &mut HashMap<OsString, Index<'a>>, name: OsString, index: Index<'a>) {
+        match index.part {
+            IndexPart::SubSeries { subseries } => {
+                for sub in subseries {
+                    let mut name = name.clone();
+                    name.push(Self::SUBSERIES_DELIMENITER);
+                    name.push(sub.name.as_ref());
+
+                    known.insert(
+                        name,
+                        Index {
+                            url: index.url.clone(),
+                            artist: index.artist.clone(),
+                            release: index.release,
+                            part: IndexPart::Direct {
+                                chapters: Chapters {
+                                    main: sub.chapters,
+                                    extra: Vec::new(),
+                                },
+                            },
+                        },
+                    );
+                }
+            }
+            IndexPart::Direct { chapters: _ } => {
+                known.insert(name, index);
+            }
+        }
+    }
     pub async fn reload(&mut self) {
-        self.data = Self::possible(&self.folder).await;
+        self.data = match &self.folder {
+            Some(folder) => Self::possible(folder).await,
+            None => HashMap::new(),
+        };
+        for path in &self.added {
+            match Index::try_read_from_path(path).await {
+                Ok(index) => Self::insert(&mut self.data, Self::series_name(path), index),
+                Err(err) => warn!("failed to reload index at {} because {err}", path.display()),
+            }
+        }
+    }
+    /// reads the index at `path` and makes it known under the name of the file or its folder
+    pub async fn add_index_file(&mut self, path: PathBuf) -> Result<OsString, Error> {
+        let index = Index::try_read_from_path(&path).await?;
+        let name = Self::series_name(&path);
+        Self::insert(&mut self.data, name.clone(), index);
+        if !self.added.contains(&path) {
+            self.added.push(path);
+        }
+        Ok(name)
     }
     pub fn get_possible(&self) -> impl IntoIterator<Item = &OsStr> {
         self.data.keys().map(OsString::as_ref).sorted()
     }
     #[must_use]
-    pub fn path(&self) -> &Path {
-        &self.folder
+    pub fn path(&self) -> Option<&Path> {
+        self.folder.as_deref()
     }
 
     pub fn has_index(&self, series: &OsString) -> bool {
@@ -475,7 +534,8 @@ impl<'a> MultiIndex<'a> {
 
     pub async fn get_index(&mut self, series: OsString) -> Result<&Index<'a>, Error> {
         if let Entry::Vacant(entry) = self.data.entry(series.clone()) {
-            entry.insert(Index::try_read_index(self.folder.clone(), series.clone()).await?);
+            let folder = self.folder.clone().ok_or(Error::SeriesNotFound)?;
+            entry.insert(Index::try_read_index(folder, series.clone()).await?);
         }
         Ok(self.data.get(&series).unwrap())
     }
@@ -826,4 +886,46 @@ mod tests {
             DateOrYear::Date(date) if date.date.unwrap().year == 2003
         ));
     }
+
+    #[tokio::test]
+    async fn added_index_survives_reload() {
+        let dir = crate::worker::test_util::test_dir("added_index_survives_reload");
+        let path = dir.join("series.txt");
+        std::fs::write(&path, "chapter 1\nchapter 2").unwrap();
+
+        let mut m_index = MultiIndex::empty();
+        assert_eq!(
+            OsString::from("series"),
+            m_index.add_index_file(path).await.unwrap()
+        );
+        m_index.reload().await;
+        assert_eq!(
+            2,
+            m_index
+                .get_known_index(&"series".into())
+                .expect("index should be known after reload")
+                .main_len()
+        );
+        assert_eq!(
+            Err(Error::SeriesNotFound),
+            m_index.get_index("other".into()).await.map(|_| ()),
+            "can't search for unknown series without a folder"
+        );
+    }
+
+    #[test]
+    fn release_after() {
+        let date = NaiveDate::from_ymd_opt(2002, 2, 2).unwrap();
+        let release = |s: &str| {
+            toml::from_str::<HashMap<String, DateOrYear>>(&format!("r = {s}"))
+                .unwrap()
+                .remove("r")
+                .unwrap()
+        };
+        assert!(release("2003").is_after(date), "later year");
+        assert!(!release("2002").is_after(date), "same year");
+        assert!(release("2002-02-03").is_after(date), "next day");
+        assert!(!release("2002-02-02").is_after(date), "same day");
+        assert!(!release("2001-12-31").is_after(date), "earlier date");
+    }
 }
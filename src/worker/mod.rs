@@ -1,21 +1,13 @@
-use audacity::AudacityApi;
 use common::{
     args::input::autocompleter,
-    extensions::{
-        iter::{FutIterExt, IteratorExt},
-        option::Ext,
-        vec::PushReturn,
-    },
+    extensions::{iter::IteratorExt, option::Ext},
 };
-use futures::TryFutureExt;
 use itertools::{Itertools, Position};
-use log::trace;
-use regex::Regex;
 use std::{
     borrow::Cow,
     collections::HashMap,
     ffi::{OsStr, OsString},
-    fmt::{Debug, Write},
+    fmt::Debug,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -24,29 +16,59 @@ use thiserror::Error;
 use toml::value::{Date, Datetime};
 
 use crate::{
-    archive::data::{build_timelabel_name, ChapterNumber},
+    archive::data::{build_timelabel_name, ChapterNumber, Source},
+    splitter::{recording::Recording, Format},
     worker::tagger::{Album, Artist, Genre, TaggedFile, Title, TotalTracks, Track, Year},
 };
 use common::args::input::Inputs;
 
 use self::{
-    args::Arguments,
+    args::{Arguments, BackendKind},
+    backend::Backend,
     index::{Index, MultiIndex},
 };
 
 pub mod args;
+pub mod backend;
+pub mod history;
 pub mod index;
+pub mod mover;
+pub mod plan;
+pub mod progress;
 pub mod tagger;
+pub mod template;
+#[cfg(test)]
+pub(crate) mod test_util;
 
 #[derive(Debug, Error)]
 #[error(transparent)]
 pub enum Error {
     Index(#[from] index::Error),
-    Move(#[from] MoveError),
+    Move(#[from] mover::Error),
     Launch(#[from] audacity::LaunchError),
     Audacity(Box<dyn std::error::Error>),
+    Split(#[from] crate::splitter::Error),
+    Plan(#[from] plan::Error),
+    Progress(#[from] progress::Error),
+    Template(#[from] template::Error),
     #[error("id3 Error {1} for {0:?}")]
     Tag(PathBuf, #[source] tagger::Error),
+    #[error("couldn't write labels to {0:?}")]
+    WriteLabels(PathBuf),
+    #[error("can't export {0:?} without audacity, only mp3 is supported")]
+    UnsupportedFormat(String),
+    #[error("no project loaded")]
+    NoProject,
+    #[error("there is no label with index {0}")]
+    NoLabel(usize),
+    #[error("couldn't open the pipe {0:?}, because {1}")]
+    Pipe(PathBuf, #[source] std::io::Error),
+    #[error("couldn't play {0:?}, because {1}")]
+    Play(PathBuf, String),
+    #[error("expecting one label track, but got {0}")]
+    LabelTracks(usize),
+    #[error("can't merge the labels:\n{}", .0.iter().join("\n"))]
+    BadLabels(Vec<BadLabel>),
 }
 impl From<audacity::ConnectionError> for Error {
     fn from(value: audacity::ConnectionError) -> Self {
@@ -77,487 +99,260 @@ enum LoopControlFlow<B, R, Res> {
     Result(Res),
 }
 
-struct LazyApi {
-    timeout: Option<Duration>,
-    cache: Option<AudacityApi>,
-}
-impl LazyApi {
-    const fn new(timeout: Option<Duration>) -> Self {
-        Self {
-            timeout,
-            cache: None,
-        }
-    }
-    pub const fn from_args(args: &Arguments) -> Self {
-        Self::new(args.timeout())
-    }
-    pub async fn get_api_handle(&mut self) -> Result<&mut AudacityApi, Error> {
-        let option = &mut self.cache;
-        Ok(match option {
-            Some(x) => x,
-            None => option.insert({
-                audacity::AudacityApiGeneric::launch(None).await?;
-                audacity::AudacityApiGeneric::new(self.timeout).await?
-            }),
-        })
-    }
-}
-mod progress {
-    use common::extensions::iter::IteratorExt;
-    use itertools::Itertools;
-    use std::path::PathBuf;
-    use tokio::{
-        fs,
-        io::{AsyncBufReadExt, AsyncSeekExt, AsyncWriteExt},
-    };
-
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-    pub enum State {
-        Loaded,
-        Named,
-        Done,
-    }
-    impl<'a> TryFrom<&'a str> for State {
-        type Error = &'a str;
-
-        fn try_from(value: &'a str) -> Result<Self, Self::Error> {
-            match value.to_ascii_lowercase().as_str() {
-                "loaded" => Ok(Self::Loaded),
-                "named" => Ok(Self::Named),
-                "done" => Ok(Self::Done),
-                _ => Err(value),
-            }
-        }
-    }
-    impl From<State> for &'static str {
-        fn from(value: State) -> Self {
-            match value {
-                State::Loaded => "loaded",
-                State::Named => "named",
-                State::Done => "done",
-            }
-        }
-    }
-
-    #[derive(Debug)]
-    pub struct Progress {
-        file: PathBuf,
-        content: Vec<(String, State)>,
-        need_save: bool,
-    }
-    #[allow(dead_code)]
-    impl Progress {
-        pub async fn read(path: impl Into<PathBuf> + Send) -> Result<Self, std::io::Error> {
-            let mut content = Vec::new();
-            let path = path.into();
-            let mut lines = tokio::io::BufReader::new(
-                fs::OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .open(&path)
-                    .await?,
-            )
-            .lines();
-            let mut line_index = 0usize;
-            while let Some(line) = lines.next_line().await? {
-                match line
-                    .rsplit_once(' ')
-                    .map(|(path, state)| (path, State::try_from(state)))
-                {
-                    None => log::warn!("can't parse {line_index}:{line:?}, will ignore"),
-                    Some((path, Err(state))) => {
-                        log::warn!("unkown state for {line_index}:{path} {state:?}, will ignore");
-                    }
-                    Some((path, Ok(state))) => {
-                        if let Some((pos, (old_path, _))) =
-                            content.iter().find_position(|&(it, _)| path == it)
-                        {
-                            log::warn!(
-                                "duplicate at {pos}:{old_path:?} {line_index}:{path:?}, forgetting old one"
-                            );
-                            content.remove(pos);
-                        }
-                        content.push((path.to_owned(), state));
-                    }
-                }
-                line_index += 1;
-            }
-
-            Ok(Self {
-                file: path,
-                content,
-                need_save: false,
-            })
-        }
-
-        pub async fn delete(self) -> std::io::Result<()> {
-            if fs::try_exists(&self.file).await? {
-                log::debug!("deleting progress file");
-                fs::remove_file(self.file).await?;
-            }
-            Ok(())
-        }
-        #[allow(clippy::unused_async)]
-        pub async fn save(&self) -> std::io::Result<()> {
-            // assumes no external change to the file
-            if !self.need_save {
-                return Ok(());
-            }
-            let mut file = tokio::io::BufWriter::new(
-                fs::OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .open(&self.file)
-                    .await?,
-            );
-            for (name, state) in &self.content {
-                file.write_all(build_line(name, *state).as_bytes()).await?;
-            }
-            file.flush().await
-        }
-        pub async fn append(
-            &mut self,
-            name: impl AsRef<str> + Send,
-            state: State,
-        ) -> std::io::Result<()> {
-            // encode if saving is needed anyway, else prepare a file to try to append to
-            let mut file = if self.need_save {
-                None
-            } else {
-                Some(
-                    fs::OpenOptions::new()
-                        .read(true)
-                        .write(true)
-                        .create(true)
-                        .open(&self.file)
-                        .await?,
-                )
-            };
-            let content_len_without_last = self.content.len().saturating_sub(1);
-            match self
-                .content
-                .iter_mut()
-                .lzip(
-                    (0..content_len_without_last)
-                        .map(Some)
-                        .chain(std::iter::once(None)),
-                )
-                .filter(|(_, (last_name, _))| last_name.as_str() == name.as_ref())
-                .last()
-            {
-                Some((None, (_, old_state))) => {
-                    *old_state = state;
-                    if let Some(file) = file.as_mut() {
-                        common::io::truncate_const_last_lines::<1>(file).await?;
-                    }
-                }
-                Some((Some(pos), _)) => {
-                    file = None; // can't just append, so full save is needet
-                    self.content.remove(pos);
-                    self.content.push((name.as_ref().to_owned(), state));
-                }
-                None => self.content.push((name.as_ref().to_owned(), state)),
-            }
-
-            if let Some(mut file) = file {
-                file.seek(std::io::SeekFrom::End(0)).await?;
-                file.write_all(build_line(name, state).as_bytes()).await?;
-                file.flush().await
-            } else {
-                Ok(())
-            }
-        }
-        pub async fn truncate_fixed<const LINES: usize>(&mut self) -> std::io::Result<()> {
-            self.content.truncate(LINES);
-            if self.need_save {
-                Ok(()) // if save is already needed, no need to modify the file
-            } else {
-                common::io::truncate_const_last_lines::<LINES>(
-                    &mut fs::OpenOptions::new()
-                        .read(true)
-                        .write(true)
-                        .create(true)
-                        .open(&self.file)
-                        .await?,
-                )
-                .await
-            }
-        }
-        pub async fn truncate(&mut self, lines: usize) -> std::io::Result<()> {
-            self.content.truncate(lines);
-            if !self.need_save {
-                // only save if not safe already needed
-                let mut file = fs::OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .open(&self.file)
-                    .await?;
-                common::io::truncate_last_lines(&mut file, lines).await?;
-            }
-            Ok(())
-        }
-
-        pub fn set(&mut self, name: String, state: State) {
-            // todo try append
-            if let Some(last) = self
-                .content
-                .iter_mut()
-                .find(|(last_name, _)| last_name.as_str() == name.as_str())
-                .map(|(_, state)| state)
-            {
-                *last = state;
-            } else {
-                self.content.push((name, state));
-            }
-            self.need_save = true;
+pub async fn run(args: &Arguments) -> Result<(), Error> {
+    match args.command() {
+        Some(args::Command::Status { dir }) => {
+            return status(dir_or_current(dir.as_deref(), args)).await;
         }
-        pub fn remove(&mut self, name: impl AsRef<str>) -> Option<(String, State)> {
-            self.content
-                .iter()
-                .find_position(|(last_name, _)| last_name.as_str() == name.as_ref())
-                .map(|(pos, _)| pos) // extra map to end borrow of self.content
-                .map(|pos| {
-                    self.need_save = true;
-                    self.content.remove(pos)
-                })
+        Some(args::Command::Reset { audio_path, to }) => {
+            return reset(args.work_dir(audio_path), audio_path, *to, args.dry_run()).await;
         }
-        pub fn get(&self, name: impl AsRef<str>) -> Option<State> {
-            self.content
-                .iter()
-                .find(|(last_name, _)| last_name.as_str() == name.as_ref())
-                .map(|(_, state)| *state)
+        Some(args::Command::Clean { dir }) => {
+            return clean(dir_or_current(dir.as_deref(), args), args.dry_run()).await;
         }
+        Some(args::Command::Run { .. }) | None => {}
     }
-    fn build_line(name: impl AsRef<str>, state: State) -> String {
-        format!("{} {state:?}\n", name.as_ref())
-    }
-
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-
-        #[tokio::test]
-        async fn read() {
-            let data = Progress::read(PathBuf::from("./res/progress.txt"))
-                .await
-                .unwrap();
-
-            assert_eq!(
-                vec![
-                    ("element 1".to_owned(), State::Done),
-                    ("element 2".to_owned(), State::Loaded),
-                    ("element 3".to_owned(), State::Done),
-                    ("element 4".to_owned(), State::Named)
-                ],
-                data.content
-            );
-        }
-        #[tokio::test]
-        async fn get() {
-            let data = Progress::read(PathBuf::from("./res/progress.txt"))
-                .await
-                .unwrap();
-
-            assert_eq!(Some(State::Done), data.get("element 1"));
-            assert_eq!(Some(State::Loaded), data.get("element 2"));
-            assert_eq!(Some(State::Done), data.get("element 3"));
-            assert_eq!(Some(State::Named), data.get("element 4"));
-            assert_eq!(None, data.get("element 5"));
-        }
-        #[tokio::test]
-        async fn append() {
-            let file = common::io::TmpFile::new_copy(
-                PathBuf::from("./res/progress_append.txt"),
-                "./res/progress.txt",
-            )
-            .unwrap();
-            let mut data = Progress::read(file.as_ref()).await.unwrap();
-            data.append("element 4", State::Done).await.unwrap();
-
-            assert_eq!(
-                Some(State::Done),
-                data.get("element 4"),
-                "failed to update internal data"
-            );
-
-            let data = Progress::read(file.as_ref()).await.unwrap();
-            assert_eq!(
-                Some(State::Done),
-                data.get("element 4"),
-                "failed to update file"
-            );
-        }
+    // checked before naming, so the work isn't lost at the export
+    if args.backend() == BackendKind::Files && args.export_ext() != Format::Mp3.ext() {
+        return Err(Error::UnsupportedFormat(args.export_ext().to_owned()));
     }
+    let mut backend: Box<dyn Backend> = match args.backend() {
+        BackendKind::Audacity => Box::new(backend::Audacity::new(args.timeout())),
+        BackendKind::Files => Box::<backend::Files>::default(),
+    };
+    run_recordings(args, backend.as_mut()).await
 }
 
-pub async fn run(args: &Arguments) -> Result<(), Error> {
+/// loads, names and exports all recordings of `args` with `backend`
+async fn run_recordings(args: &Arguments, backend: &mut dyn Backend) -> Result<(), Error> {
+    let recordings = Recording::group(args.audio_paths().iter().cloned());
     assert!(
-        !args.skip_load() || args.audio_paths().len() == 1,
+        !args.skip_load() || recordings.len() == 1,
         "skipping only allowed with single audio"
     );
-    let mut audacity_api = LazyApi::from_args(args);
+    assert!(
+        args.plan().is_none() || recordings.len() == 1,
+        "plan only allowed with single audio"
+    );
+    let mut plan = args.plan().map(plan::Plan::read).transpose()?;
     let mut m_index = match args.index_folder() {
-        Some(path) => Some((MultiIndex::new(path.to_owned())).await),
-        None => None,
+        Some(path) => MultiIndex::new(path.to_owned()).await,
+        None => MultiIndex::empty(),
     };
-    let mut already_done = progress::Progress::read(args.tmp_path().join(".done.txt"))
-        .await
-        .unwrap();
-
-    let re = Regex::new(r"\((d+)\)(.[a-zA-Z0-9]+)?$").unwrap();
-
-    for (pos, audio_path) in args.audio_paths().iter().with_position() {
-        let name = audio_path
+    for (pos, recording) in recordings.iter().with_position() {
+        let name = recording
+            .path()
             .file_name()
             .unwrap()
             .to_string_lossy()
             .into_owned();
-
-        if re.is_match(&name) {
-            log::info!("skipping sub file");
-            // TODO maybe run main file
+        let work_dir = args.work_dir(recording.path());
+        let mut progress = progress::Progress::read_in(work_dir).await?;
+        let label_path = recording.label_path();
+        if progress.get(&recording.joined_path(work_dir)) == Some(progress::State::Moved) {
+            // the joined audio might already be cleaned up, so don't look for changes
+            log::info!("{name} is already done");
             continue;
         }
-
-        let label_path = audio_path.with_extension("txt");
-
-        let audacity_api = audacity_api.get_api_handle().await?;
-        let state = already_done.get(&name);
-
-        if !args.skip_load() && state.is_none_or(|state| state < progress::State::Loaded) {
-            prepare_project(audacity_api, audio_path, &label_path).await?;
-            already_done
-                .append(&name, progress::State::Loaded)
-                .await
-                .unwrap();
-        } else {
-            log::debug!("skipping load");
+        let audio_path = &recording.joined_path(work_dir);
+        if recording.is_split() {
+            if args.dry_run() && !(audio_path.exists() && label_path.exists()) {
+                log::warn!("dry run, the parts of {name} aren't joined, skipping it");
+                continue;
+            }
+            log::info!("joining {} parts of {name}", recording.parts().len());
+            recording.join(work_dir, args.dry_run())?;
+            if !label_path.exists() {
+                let labels = recording.read_labels()?;
+                audacity::data::TimeLabel::write(labels, &label_path, args.dry_run())
+                    .map_err(|_| Error::WriteLabels(label_path.clone()))?;
+            }
         }
 
-        // start rename
-        if !args.skip_name() && state.is_none_or(|state| state < progress::State::Named) {
-            audacity_api
-                .zoom_to(
-                    audacity::data::Selection::All,
-                    audacity::data::Save::Discard,
-                )
-                .await?;
-            let _ = Inputs::read("press enter when you are ready to start renaming", None);
-
-            if let Some(m_index) = m_index.as_mut() {
-                // explicit binder, so Future is Send
-                let mut binder = rename_labels::FancyNamer::new(audacity_api, m_index).await?;
-                binder.rename().await?;
-            } else {
-                // TODO clean else path
-                rename_labels::old(args, audacity_api).await?;
-                rename_labels::adjust_labels(audacity_api).await?;
+        match progress.changed(audio_path, &label_path).await? {
+            Some(progress::Change::Audio) => {
+                log::warn!("{name} changed since its last run, starting over");
+                progress.remove(audio_path);
             }
+            Some(progress::Change::Labels) => {
+                log::warn!("labels of {name} changed since its last run, starting over");
+                progress.remove(audio_path);
+            }
+            None => {}
+        }
+        let state = progress.get(audio_path);
+
+        let result = run_file(
+            args,
+            backend,
+            &mut progress,
+            &mut m_index,
+            &mut plan,
+            recording,
+            state,
+        )
+        .await;
+        match &result {
+            Err(err) if !args.dry_run() => progress.fail(audio_path, err).await?,
+            _ => {}
+        }
+        result?;
 
-            audacity_api
-                .zoom_to(
-                    audacity::data::Selection::All,
-                    audacity::data::Save::Discard,
-                )
-                .await?;
-            audacity_api
-                .export_all_labels_to(label_path, args.dry_run())
+        if !args.skip_load() {
+            backend
+                .close(matches!(pos, Position::Last | Position::Only))
                 .await?;
-
-            already_done
-                .append(&name, progress::State::Named)
-                .await
-                .unwrap();
-        } else {
-            log::debug!("skipping naming");
         }
-        if state.is_none_or(|state| state < progress::State::Done) {
-            //start export
-            let tags = merge_parts(
-                args,
-                audacity_api,
-                m_index.as_mut().expect("need index"),
-                audacity::data::TrackHint::LabelTrackNr(0),
-            )
-            .await?;
-            let _ = Inputs::read(
-                // "remove all lables you don't want to export and then press enter to start exporting",
-                "remove all lables you don't want to remove, then press Ctrl+Shift+E to export and then press enter to continue",
-                None,
-            );
-            // TODO find out how to fix "Ihr Stapelverarbeitungs-Befehl ExportAudio wurde nicht erkannt."
-            // audacity_api
-            //     .write_assume_empty(audacity::command::ExportAudio)
-            //     .await?;
-
-            let (mut tags, missing) = tags
-                .into_iter()
-                .partition::<Vec<_>, _>(|tag| tag.path().exists());
-
-            missing.into_iter().for_each(TaggedFile::drop_changes);
+    }
+    // download of progress done in external script
+    Ok(())
+}
 
-            if tags.is_empty() {
-                log::warn!("no files exported, skipping move");
-            } else {
-                for tag in &mut tags {
-                    tag.reload_empty()
-                        .map_err(|err| Error::Tag(tag.path().into(), err))?;
-                    tag.save_changes(false)
-                        .map_err(|err| Error::Tag(tag.path().into(), err))?;
-                }
-                move_results(
-                    tags.iter(),
-                    args.tmp_path(),
-                    args.index_folder().unwrap_or_else(|| args.tmp_path()),
-                    args,
-                )
+/// loads, names and exports one recording, starting after `state`
+async fn run_file(
+    args: &Arguments,
+    backend: &mut dyn Backend,
+    progress: &mut progress::Progress,
+    m_index: &mut MultiIndex,
+    plan: &mut Option<plan::Plan>,
+    recording: &Recording,
+    state: Option<progress::State>,
+) -> Result<(), Error> {
+    let work_dir = args.work_dir(recording.path());
+    let audio_path = &recording.joined_path(work_dir);
+    let label_path = recording.label_path();
+
+    let needs_load = !args.skip_load() && state.is_none_or(|state| state < progress::State::Loaded);
+    if needs_load || !backend.keeps_project() {
+        backend.load(audio_path, &label_path).await?;
+        // a dry run leaves the progress untouched, so the next run starts at the same step
+        if needs_load && !args.dry_run() {
+            progress
+                .reach(audio_path, &label_path, progress::State::Loaded)
                 .await?;
+            if recording.is_split() {
+                progress.set_parts(audio_path, recording.parts()).await?;
             }
-            drop(tags);
+        }
+    } else {
+        log::debug!("skipping load");
+    }
 
-            already_done
-                .append(name, progress::State::Done)
-                .await
-                .unwrap();
-        } else {
-            log::debug!("skipping export");
+    // start rename
+    if !args.skip_name() && state.is_none_or(|state| state < progress::State::Named) {
+        backend.zoom_all().await?;
+        if plan.is_none() {
+            let _ = Inputs::read("press enter when you are ready to start renaming", None);
         }
 
-        if !args.skip_load() {
-            // clear audacity after each round, but exit in last round
-            audacity_api
-                .write_assume_empty(match pos {
-                    Position::First | Position::Middle => audacity::command::Close,
-                    Position::Last | Position::Only => audacity::command::Exit,
-                })
+        let air_date = Source::from_path(recording.path()).ok().map(|it| it.date());
+        // explicit binder, so Future is Send
+        let mut binder =
+            rename_labels::FancyNamer::new(backend, m_index, plan.take(), air_date).await?;
+        binder.rename().await?;
+
+        backend.zoom_all().await?;
+        backend.export_labels(&label_path, args.dry_run()).await?;
+        if recording.is_split() {
+            // so the parts can be named again on their own
+            recording.write_part_labels(&backend.labels().await?, args.dry_run())?;
+        }
+
+        if !args.dry_run() {
+            progress
+                .reach(audio_path, &label_path, progress::State::Named)
                 .await?;
         }
+    } else {
+        log::debug!("skipping naming");
     }
-    // download of progress done in external script
+    if state.is_none_or(|state| state < progress::State::Moved) {
+        //start export
+        let resumed = state.is_some_and(|state| state >= progress::State::Merged);
+        let labels = if resumed {
+            // the project might already contain the merged labels, so use the named ones
+            crate::splitter::read_labels(&label_path)?
+        } else {
+            backend.labels().await?
+        };
+        let plan = plan_export(args, work_dir, &labels, m_index).await?;
+        print_plan(&plan);
+        if args.dry_run() {
+            log::info!("dry run, skipping export");
+        } else if resumed || args.always_answer().ask_consent("export as planned?") {
+            export(args, backend, &plan, progress, recording, state).await?;
+        } else {
+            log::warn!(
+                "skipping export of {:?}",
+                recording.path().file_name().unwrap_or_default()
+            );
+        }
+    } else {
+        log::debug!("skipping export");
+    }
+    Ok(())
+}
+
+/// `dir`, or the configured work directory, or the current one
+fn dir_or_current<'a>(dir: Option<&'a Path>, args: &'a Arguments) -> &'a Path {
+    dir.or_else(|| args.configured_work_dir())
+        .unwrap_or_else(|| Path::new("."))
+}
+
+/// prints the progress of all recordings in `dir`
+pub async fn status(dir: &Path) -> Result<(), Error> {
+    let progress = progress::Progress::read_in(dir).await?;
+    print!("{progress}");
     Ok(())
 }
 
-async fn prepare_project(
-    audacity: &mut AudacityApi,
-    audio_path: impl AsRef<Path> + Send,
-    label_path: impl AsRef<Path> + Send + Sync,
+/// goes back to `to` for `audio_path`, or forgets it completely. The progress is stored in `dir`.
+/// A split recording is found by any of its parts or by its name without part number
+pub async fn reset(
+    dir: &Path,
+    audio_path: &Path,
+    to: Option<progress::State>,
+    dry_run: bool,
 ) -> Result<(), Error> {
-    trace!("opened audacity");
-    if audacity.get_track_info().await?.is_empty() {
-        trace!("no need to open new project");
+    let mut progress = progress::Progress::read_in(dir).await?;
+    let old = match to {
+        Some(to) => progress.set(audio_path, to),
+        None => progress.remove(audio_path).map(|it| it.state),
+    };
+    match (old, to) {
+        (None, _) => log::warn!("no progress for {audio_path:?}"),
+        (Some(old), Some(to)) if old < to => {
+            log::warn!("{audio_path:?} is only {old}, can't go forward to {to}");
+            return Ok(());
+        }
+        (Some(old), Some(to)) => println!("reset {audio_path:?} from {old} to {to}"),
+        (Some(old), None) => println!("forgot {audio_path:?}, it was {old}"),
+    }
+    if dry_run {
+        log::info!("dry run, not saving");
+        Ok(())
     } else {
-        audacity.write_assume_empty(audacity::command::New).await?;
-        trace!("opened new project");
+        Ok(progress.save().await?)
+    }
+}
+
+/// forgets the progress of every recording in `dir`, that no longer exists
+pub async fn clean(dir: &Path, dry_run: bool) -> Result<(), Error> {
+    let mut progress = progress::Progress::read_in(dir).await?;
+    let removed = progress.clean();
+    if removed.is_empty() {
+        println!("nothing to clean");
+        return Ok(());
+    }
+    for key in &removed {
+        println!("forgot {key:?}");
+    }
+    if dry_run {
+        log::info!("dry run, not saving");
+        Ok(())
+    } else {
+        Ok(progress.save().await?)
     }
-    audacity.import_audio(audio_path).await?;
-    trace!("loaded audio");
-    audacity
-        .import_labels_from(label_path, None::<&str>)
-        .await?;
-    Ok(())
 }
 
 #[derive(Debug)]
@@ -660,18 +455,25 @@ impl<'a> autocompleter::Autocomplete for ChapterCompleter<'a> {
 
 mod rename_labels {
     use itertools::Itertools;
-    use std::{borrow::Cow, path::PathBuf, str::FromStr, time::Duration};
+    use std::{path::PathBuf, str::FromStr, time::Duration};
 
-    use audacity::{data::TimeLabel, AudacityApi};
+    use audacity::data::TimeLabel;
+    use chrono::NaiveDate;
     use common::{
         args::input::{autocompleter, Inputs},
         extensions::iter::{CloneIteratorExt, State},
     };
 
-    use super::{args::Arguments, ChapterCompleter, Error};
+    use super::{
+        backend::Backend,
+        format_time,
+        history::{Edit, History, LabelData},
+        plan::Plan,
+        ChapterCompleter, Error,
+    };
     use crate::{
-        archive::data::{build_timelabel_name, ChapterNumber},
-        worker::index::{Error as IdxError, Index, MultiIndex},
+        archive::data::{build_timelabel_name, Archive, ChapterNumber},
+        worker::index::MultiIndex,
     };
 
     #[derive(Debug)]
@@ -771,170 +573,98 @@ mod rename_labels {
         }
     }
 
-    #[tokio::test]
-    #[ignore = "user input test"]
-    async fn full_ac_test() {
-        let mut m_index =
-            MultiIndex::new("/home/nilsj/Musik/newly ripped/Aufnahmen/current".into()).await;
-        let ac = FullNameCompleter::new(&mut m_index, common::str::filter::Levenshtein::new(true));
-        let res =
-            common::args::input::Inputs::read_with_suggestion("gib ein Kapitel an:", None, ac);
-        println!("{res:?} wurde gelesen");
-    }
-
     ///expecting that number of parts divides the length of the input or default to 4
     const EXPECTED_PARTS: [usize; 13] = [0, 1, 2, 3, 4, 3, 3, 4, 4, 3, 5, 4, 4];
-    const ASK_ALL_MSG: &str = "Was ist die n\u{e4}chste Folge:";
-    const ASK_PARTS_MSG: &str = "Wie viele Teile hat die n\u{e4}chste Folge";
-    const ASK_NUMBER_MSG: &str = "Welche Nummer hat die n\u{e4}chste Folge";
-    const MSG: &str = "Welche Serie ist heute dran:";
-
-    async fn get_labels(api: &mut AudacityApi) -> Result<Vec<TimeLabel>, Error> {
-        let labels = api.get_label_info().await?;
-        Ok::<_, Error>(
-            labels
-                .into_values()
-                .exactly_one()
-                .unwrap_or_else(|err| panic!("expecting one label track, but got {}", err.len())),
-        )
-    }
-    fn request_next_chapter_name() -> String {
-        Inputs::read("Wie hei\u{df}t die n\u{e4}chste Folge: ", None)
-    }
-    fn read_number(input: Inputs, msg: impl AsRef<str>, default: Option<usize>) -> usize {
-        input
-            .try_read(msg, default, |rin| rin.parse().ok())
-            .expect("gib was vern\u{fc}nftiges ein")
-    }
-
-    async fn read_index_from_args<'i>(
-        args: &Arguments,
-    ) -> Result<(String, Option<Index<'i>>), IdxError> {
-        let series = Inputs::read(MSG, None);
-        if let Some(value) = series
-            .strip_prefix('#')
-            .map(|series| series[1..].to_owned())
-        {
-            return Ok((value, None));
-        }
+    const ASK_ALL_MSG: &str = "what is the next chapter:";
+    /// how much of a label is played by `play-start` and `play-end` without an explicit length
+    const PREVIEW_LENGTH: Duration = Duration::from_secs(10);
 
-        let index = loop {
-            let path = args
-                .always_answer()
-                .try_read(
-                    "welche Index Datei m\u{f6}chtest du verwenden?: ",
-                    Some(None),
-                    |it| Some(Some(PathBuf::from(it))),
-                )
-                .unwrap_or_else(|| unreachable!());
-            match path {
-                None => break None,
-                Some(path) => {
-                    let map = crate::worker::index::Index::try_read_from_path(path).await;
-                    match map {
-                        Ok(index) => break Some(index),
-                        Err(IdxError::SeriesNotFound) => unreachable!(),
-                        Err(IdxError::NoIndexFile | IdxError::NotSupportedFile(_)) => {
-                            println!("couldn't find requested index, try again");
-                            continue;
-                        }
-                        Err(IdxError::Parse(_, _) | IdxError::Serde(_) | IdxError::IO(_, _)) => {
-                            // SAFETY: we are in the error case
-                            Err(unsafe { map.unwrap_err_unchecked() })?;
-                        }
-                    }
-                }
-            };
-        };
-        Ok((series, index))
+    fn request_next_chapter_name() -> String {
+        Inputs::read("what is the title of the next chapter: ", None)
     }
+    /// where to split a label, either absolute or relative to its start when prefixed with `+`
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    struct SplitAt {
+        relative: bool,
+        time: Duration,
+    }
+    impl FromStr for SplitAt {
+        type Err = crate::args::NoMatch;
 
-    pub async fn old(args: &Arguments, api: &mut audacity::AudacityApi) -> Result<(), Error> {
-        let labels = get_labels(api).await?;
-        let (series, index) = read_index_from_args(args).await?;
-        let index = index.as_ref();
-        let mut ac = index.as_ref().map(|&index| {
-            ChapterCompleter::new(index, common::str::filter::Levenshtein::new(true))
-        });
-
-        let mut expected_next_chapter_number: Option<ChapterNumber> = None;
-        let mut i = 0;
-        while i < labels.len() {
-            let chapter_number = match ac.as_mut() {
-                Some(index) => {
-                    let input = Inputs::read_with_suggestion(
-                        format!("{ASK_NUMBER_MSG}:"),
-                        expected_next_chapter_number
-                            .map(|it| it.to_string())
-                            .as_deref(),
-                        index,
-                    );
-                    input
-                        .split_once(' ')
-                        .map_or(input.as_ref(), |it| it.0)
-                        .parse::<ChapterNumber>()
-                        .ok()
-                }
-                None => args.always_answer().try_read(
-                    &format!(
-                        "{ASK_NUMBER_MSG}{}: ",
-                        expected_next_chapter_number
-                            .map_or_else(String::new, |next| format!(", erwarte {next}"))
-                    ),
-                    expected_next_chapter_number,
-                    |rin| rin.parse::<ChapterNumber>().ok(),
-                ),
-            }
-            .expect("gib was vern\u{fc}nftiges ein");
-            expected_next_chapter_number = Some(chapter_number.next());
-
-            let chapter_name = index.map_or_else(
-                || Cow::Owned(request_next_chapter_name()),
-                |it| it.get(chapter_number).title,
-            );
-
-            let remaining = labels.len() - i;
-            let expected_number = EXPECTED_PARTS
-                .get(labels.len())
-                .map_or(4, |i| *i)
-                .min(remaining);
-            let number = read_number(
-                args.always_answer(),
-                &format!("{ASK_PARTS_MSG}, erwarte {expected_number}: "),
-                Some(expected_number),
-            )
-            .min(remaining);
-            for j in 0..number {
-                let name = build_timelabel_name::<str, _, _>(
-                    &series,
-                    &chapter_number,
-                    j + 1,
-                    chapter_name.as_ref(),
-                );
-                api.set_label(i + j, Some(name), None, None, Some(false))
-                    .await?;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let (relative, time) = s.strip_prefix('+').map_or((false, s), |it| (true, it));
+            Ok(Self {
+                relative,
+                time: crate::args::parse_duration(time)?,
+            })
+        }
+    }
+    impl SplitAt {
+        fn resolve(self, label: &TimeLabel) -> Duration {
+            if self.relative {
+                *label.start() + self.time
+            } else {
+                self.time
             }
-            i += number;
         }
-        Ok(())
     }
 
-    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    #[derive(Debug, Clone, Eq, PartialEq)]
     enum Command {
         ReloadIndex,
+        Index(PathBuf),
         ReloadLabel,
         Restart,
         Join,
+        Split(Option<SplitAt>),
+        Delete,
+        Undo,
+        Redo,
+        Back,
+        Goto(usize),
+        Skip,
+        List,
+        Play,
+        PlayStart(Option<Duration>),
+        PlayEnd(Option<Duration>),
     }
     impl FromStr for Command {
         type Err = String;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            match s {
-                "reload_label" => Ok(Self::ReloadLabel),
-                "reload_index" => Ok(Self::ReloadIndex),
-                "resize" => Ok(Self::Restart),
-                "join" => Ok(Self::Join),
+            let (command, arg) = s
+                .trim()
+                .split_once(' ')
+                .map_or((s.trim(), None), |(command, arg)| {
+                    (command, Some(arg.trim()))
+                });
+            match (command, arg) {
+                ("reload_label", None) => Ok(Self::ReloadLabel),
+                ("reload_index", None) => Ok(Self::ReloadIndex),
+                ("index", Some(path)) => Ok(Self::Index(PathBuf::from(path))),
+                ("resize", None) => Ok(Self::Restart),
+                ("join", None) => Ok(Self::Join),
+                ("split", None) => Ok(Self::Split(None)),
+                ("split", Some(at)) => at
+                    .parse()
+                    .map(|at| Self::Split(Some(at)))
+                    .map_err(|_| s.to_owned()),
+                ("delete", None) => Ok(Self::Delete),
+                ("undo", None) => Ok(Self::Undo),
+                ("redo", None) => Ok(Self::Redo),
+                ("back", None) => Ok(Self::Back),
+                ("goto", Some(i)) => i.parse().map(Self::Goto).map_err(|_| s.to_owned()),
+                ("skip", None) => Ok(Self::Skip),
+                ("list", None) => Ok(Self::List),
+                ("play", None) => Ok(Self::Play),
+                ("play-start", None) => Ok(Self::PlayStart(None)),
+                ("play-end", None) => Ok(Self::PlayEnd(None)),
+                ("play-start", Some(len)) => crate::args::parse_duration(len)
+                    .map(|len| Self::PlayStart(Some(len)))
+                    .map_err(|_| s.to_owned()),
+                ("play-end", Some(len)) => crate::args::parse_duration(len)
+                    .map(|len| Self::PlayEnd(Some(len)))
+                    .map_err(|_| s.to_owned()),
                 _ => Err(s.to_owned()),
             }
         }
@@ -944,65 +674,301 @@ mod rename_labels {
             match value {
                 Command::ReloadLabel => "reload_label",
                 Command::ReloadIndex => "reload_index",
+                Command::Index(_) => "index",
                 Command::Restart => "resize",
                 Command::Join => "join",
+                Command::Split(_) => "split",
+                Command::Delete => "delete",
+                Command::Undo => "undo",
+                Command::Redo => "redo",
+                Command::Back => "back",
+                Command::Goto(_) => "goto",
+                Command::Skip => "skip",
+                Command::List => "list",
+                Command::Play => "play",
+                Command::PlayStart(_) => "play-start",
+                Command::PlayEnd(_) => "play-end",
             }
         }
     }
     impl Command {
-        fn iter() -> std::array::IntoIter<Self, 4> {
+        fn iter() -> std::array::IntoIter<Self, 16> {
             [
                 Self::ReloadIndex,
+                Self::Index(PathBuf::new()),
                 Self::ReloadLabel,
                 Self::Restart,
                 Self::Join,
+                Self::Split(None),
+                Self::Delete,
+                Self::Undo,
+                Self::Redo,
+                Self::Back,
+                Self::Goto(0),
+                Self::Skip,
+                Self::List,
+                Self::Play,
+                Self::PlayStart(None),
+                Self::PlayEnd(None),
             ]
             .into_iter()
         }
     }
 
+    /// splits the `i`-th label at `at` and moves the parts of the following labels of the same chapter one up
+    fn split_edit(labels: &[TimeLabel], i: usize, at: Duration) -> Option<Edit> {
+        let label = &labels[i];
+        if at <= *label.start() || *label.end() <= at {
+            return None;
+        }
+        let parsed = label.name().and_then(Archive::parse_line);
+        let mut renames = Vec::new();
+        let name = match parsed {
+            Some((series, nr, part, chapter)) => {
+                if part.is_none() {
+                    renames.push(Edit::Rename {
+                        i,
+                        old: label.name().map(ToOwned::to_owned),
+                        new: build_timelabel_name::<str, _, _>(series, &nr, 1, chapter),
+                    });
+                }
+                let part = part.unwrap_or(1);
+                for (j, following) in labels.iter().enumerate().skip(i + 1) {
+                    match following.name().and_then(Archive::parse_line) {
+                        Some((f_series, f_nr, Some(f_part), f_chapter))
+                            if f_series == series && f_nr == nr =>
+                        {
+                            renames.push(Edit::Rename {
+                                i: j + 1,
+                                old: following.name().map(ToOwned::to_owned),
+                                new: build_timelabel_name::<str, _, _>(
+                                    f_series,
+                                    &f_nr,
+                                    f_part + 1,
+                                    f_chapter,
+                                ),
+                            });
+                        }
+                        _ => break,
+                    }
+                }
+                Some(build_timelabel_name::<str, _, _>(
+                    series,
+                    &nr,
+                    part + 1,
+                    chapter,
+                ))
+            }
+            None => label.name().map(ToOwned::to_owned),
+        };
+        let mut edits = vec![
+            Edit::Resize {
+                i,
+                old_end: *label.end(),
+                new_end: at,
+            },
+            Edit::Insert {
+                i: i + 1,
+                label: LabelData {
+                    start: at,
+                    end: *label.end(),
+                    name,
+                },
+            },
+        ];
+        edits.extend(renames);
+        Some(Edit::Batch(edits))
+    }
+
+    type LastRead = Option<(String, ChapterNumber, usize, String)>;
+
+    /// what would have been read for `label`, if it already has a full name
+    fn read_from(label: &TimeLabel) -> LastRead {
+        let (series, nr, part, chapter) = Archive::parse_line(label.name()?)?;
+        Some((
+            series.to_owned(),
+            nr,
+            part.unwrap_or(1),
+            chapter?.to_owned(),
+        ))
+    }
+
     pub struct FancyNamer<'a, 'r, 'i> {
-        api: &'a mut AudacityApi,
+        backend: &'a mut dyn Backend,
         m_index: &'r mut MultiIndex<'i>,
         labels: Vec<TimeLabel>,
-        last_read: Option<(String, ChapterNumber, usize, String)>,
+        plan: Option<Plan>,
+        history: History<(usize, LastRead)>,
+        last_read: LastRead,
+        air_date: Option<NaiveDate>,
         i: usize,
     }
     impl<'a, 'r, 'i> FancyNamer<'a, 'r, 'i> {
         pub async fn new(
-            api: &'a mut AudacityApi,
+            backend: &'a mut dyn Backend,
             m_index: &'r mut MultiIndex<'i>,
+            plan: Option<Plan>,
+            air_date: Option<NaiveDate>,
         ) -> Result<Self, Error> {
-            let labels = get_labels(api).await?;
+            let labels = backend.labels().await?;
             Ok(Self {
-                api,
+                backend,
                 m_index,
                 labels,
+                plan,
+                history: History::default(),
                 last_read: None,
+                air_date,
                 i: 0,
             })
         }
 
-        pub async fn rename(&mut self) -> Result<(), Error> {
-            while self.i < self.labels.len() {
-                zoom_to_label(
-                    self.api,
-                    self.labels.iter().open_border_pairs().nth(self.i).unwrap(),
-                )
-                .await?;
-                let (series, chapter_number, chapter_name, part) = loop {
-                    let initial = self.last_read.as_ref().map(|(series, nr, _, chapter)| {
-                        if self.m_index.has_index(&series.into()) {
-                            format!("{series} {nr}")
-                        } else {
-                            format!("{series} {nr} {chapter}") // keep chapter when no index is found
-                        }
-                    });
-                    let mut ac = FullNameCompleter::new(
-                        self.m_index,
-                        common::str::filter::Levenshtein::new(true),
-                    );
-                    if let Some((series, _, _, _)) = self.last_read.as_ref() {
+        /// applies `edit` and remembers it with the `before` state, so it can be undone
+        async fn edit(&mut self, edit: Edit, before: (usize, LastRead)) -> Result<(), Error> {
+            edit.apply(self.backend).await?;
+            self.labels = self.backend.labels().await?;
+            self.history.record(edit, before);
+            Ok(())
+        }
+        fn rename_edit(&self, new: String) -> Edit {
+            Edit::Rename {
+                i: self.i,
+                old: self.labels[self.i].name().map(ToOwned::to_owned),
+                new,
+            }
+        }
+
+        /// moves to the `i`-th label and restores what was read up to it
+        fn goto(&mut self, i: usize) {
+            self.i = i.min(self.labels.len());
+            self.last_read = self
+                .i
+                .checked_sub(1)
+                .and_then(|prev| read_from(&self.labels[prev]));
+        }
+        /// all labels with their times, the current one is marked with '>'
+        fn listing(&self) -> String {
+            self.labels
+                .iter()
+                .enumerate()
+                .map(|(i, label)| {
+                    format!(
+                        "{} {i:>3} {} - {} {}",
+                        if i == self.i { '>' } else { ' ' },
+                        format_time(*label.start()),
+                        format_time(*label.end()),
+                        label.name().unwrap_or_default()
+                    )
+                })
+                .join("\n")
+        }
+
+        /// the part following the last read label, if it belongs to the same chapter
+        fn next_part(&self, series: &str, nr: ChapterNumber) -> usize {
+            self.last_read
+                .as_ref()
+                .filter(|(last_series, last_nr, _, _)| last_series == series && *last_nr == nr)
+                .map_or(1, |(_, _, last_part, _)| last_part + 1)
+        }
+
+        /// the number of parts the chapters of this recording probably have
+        fn expected_parts(&self) -> usize {
+            // use the last finished chapter, the first might have started before the recording
+            self.labels[..self.i]
+                .iter()
+                .map(read_from)
+                .tuple_windows()
+                .filter_map(|(current, next)| match (current, next) {
+                    (Some((series, nr, part, _)), Some((next_series, next_nr, _, _)))
+                        if (series, nr) != (next_series, next_nr) =>
+                    {
+                        Some(part)
+                    }
+                    _ => None,
+                })
+                .last()
+                .unwrap_or_else(|| EXPECTED_PARTS.get(self.labels.len()).map_or(4, |i| *i))
+        }
+
+        /// the most likely name of the current label, the next part of the last chapter or the next chapter
+        fn suggestion(&mut self) -> Option<String> {
+            let (series, nr, part, chapter) = self.last_read.as_ref()?;
+            let has_index = self.m_index.has_index(&series.into());
+            if *part < self.expected_parts() {
+                return Some(if has_index {
+                    format!("{series} {nr}")
+                } else {
+                    format!("{series} {nr} {chapter}") // keep chapter when no index is found
+                });
+            }
+            let next = nr.next();
+            let Some(index) = self.m_index.get_known_index(&series.into()) else {
+                return Some(format!("{series} {next}"));
+            };
+            let entry = index.try_get(next)?;
+            if let Some(air_date) = self.air_date {
+                if entry
+                    .release
+                    .is_some_and(|release| release.is_after(air_date))
+                {
+                    log::info!("{series} {next} was released after the recording from {air_date}");
+                    return Some(format!("{series} "));
+                }
+            }
+            Some(format!("{series} {next}"))
+        }
+
+        /// the name offered for the current label, its current name when revisiting a named label
+        fn initial(&mut self) -> Option<String> {
+            read_from(&self.labels[self.i])
+                .and(self.labels[self.i].name().map(ToOwned::to_owned))
+                .or_else(|| self.suggestion())
+        }
+
+        /// the name of the current label from the plan, `None` if it needs to be asked
+        async fn planned(&mut self) -> Option<(String, ChapterNumber, String, usize)> {
+            let entry = self.plan.as_mut()?.remove(self.i)?;
+            let chapter = match entry.title {
+                Some(title) => title,
+                None => match self.m_index.get_index(entry.series.as_str().into()).await {
+                    Ok(index) => index.try_get(entry.chapter)?.title.into_owned(),
+                    Err(err) => {
+                        log::warn!("no title for planned label {}, because {err}", self.i);
+                        return None;
+                    }
+                },
+            };
+            let part = entry
+                .part
+                .unwrap_or_else(|| self.next_part(&entry.series, entry.chapter));
+            Some((entry.series, entry.chapter, chapter, part))
+        }
+
+        pub async fn rename(&mut self) -> Result<(), Error> {
+            let mut asked = false;
+            'labels: while self.i < self.labels.len() {
+                let before = (self.i, self.last_read.clone());
+                if let Some((series, nr, chapter, part)) = self.planned().await {
+                    log::info!("using planned name for label {}", self.i);
+                    let name = build_timelabel_name::<str, _, _>(&series, &nr, part, &chapter);
+                    self.edit(self.rename_edit(name), before).await?;
+                    self.last_read = Some((series, nr, part, chapter));
+                    self.i += 1;
+                    continue;
+                }
+                asked = true;
+                zoom_to_label(
+                    self.backend,
+                    self.labels.iter().open_border_pairs().nth(self.i).unwrap(),
+                )
+                .await?;
+                let (series, chapter_number, chapter_name, part) = loop {
+                    let initial = self.initial();
+                    let mut ac = FullNameCompleter::new(
+                        self.m_index,
+                        common::str::filter::Levenshtein::new(true),
+                    );
+                    if let Some((series, _, _, _)) = self.last_read.as_ref() {
                         ac.state = CompleterState::Series(series.clone());
                     }
                     let command_prefix = ac.command_prefix;
@@ -1015,7 +981,7 @@ mod rename_labels {
                     match res.strip_prefix(command_prefix).map(str::parse) {
                         Some(Ok(command)) => {
                             self.run_command(command).await?;
-                            continue;
+                            continue 'labels;
                         }
                         Some(Err(command)) => {
                             println!("unkown command {command:?}");
@@ -1033,35 +999,26 @@ mod rename_labels {
                                 Err(_) => request_next_chapter_name(),
                             },
                         };
-                        let part = self
-                            .last_read
-                            .as_ref()
-                            .filter(|(last_series, last_nr, _, _)| {
-                                last_series == series && *last_nr == nr
-                            })
-                            .map_or(1, |(_, _, last_part, _)| last_part + 1);
+                        let part = self.next_part(series, nr);
                         self.last_read = Some((series.to_owned(), nr, part, chapter.clone()));
                         break (series.to_owned(), nr, chapter, part);
                     }
-                    println!("konnte {res} nicht erkennen");
+                    println!("couldn't recognize {res}");
                 };
 
                 let name =
                     build_timelabel_name::<str, _, _>(series, &chapter_number, part, &chapter_name);
-                self.api
-                    .set_label(self.i, Some(name), None, None, Some(false))
-                    .await?;
+                self.edit(self.rename_edit(name), before).await?;
                 self.i += 1;
             }
-            zoom_to_label(
-                self.api,
-                self.labels.iter().open_border_pairs().last().unwrap(),
-            )
-            .await?;
-            let _ = Inputs::read(
-                "Dr\u{fc}ck Enter, wenn du bereit f\u{fc}r den n\u{e4}chsten Schritt bist",
-                None,
-            );
+            if asked {
+                zoom_to_label(
+                    self.backend,
+                    self.labels.iter().open_border_pairs().last().unwrap(),
+                )
+                .await?;
+                let _ = Inputs::read("press enter when you are ready for the next step", None);
+            }
             Ok(())
         }
 
@@ -1070,9 +1027,15 @@ mod rename_labels {
                 Command::ReloadIndex => {
                     self.m_index.reload().await;
                 }
+                Command::Index(path) => match self.m_index.add_index_file(path).await {
+                    Ok(series) => println!("added index for {series:?}"),
+                    Err(err) => println!("couldn't add index, because {err}"),
+                },
                 Command::ReloadLabel => {
                     let old_i = self.labels.remove(self.i);
-                    self.labels = get_labels(self.api).await?;
+                    self.labels = self.backend.labels().await?;
+                    // the edits refer to the indices of the labels before the reload
+                    self.history = History::default();
 
                     if self.labels.get(self.i).is_some_and(|label| *label != old_i) {
                         if let Some((i, _)) = self.labels.iter().find_position(|&it| *it == old_i) {
@@ -1083,244 +1046,797 @@ mod rename_labels {
                 Command::Restart => {
                     self.i = 0;
                     self.last_read = None;
-                    self.labels = get_labels(self.api).await?;
+                    self.labels = self.backend.labels().await?;
+                    self.history = History::default();
                 }
                 Command::Join => {
                     if self.i == 0 {
                         log::warn!("can't join first");
                         return Ok(());
                     }
-                    // assumes no overlapping labels in this track so no reload of labels is needed
-                    let label_delete = self.labels.remove(self.i);
-                    self.api
-                        .select(audacity::data::Selection::Part {
-                            start: *label_delete.start(),
-                            end: *label_delete.end(),
-                            relative_to: audacity::data::RelativeTo::ProjectStart,
-                        })
-                        .await?;
-                    self.api.select_tracks(std::iter::once(1)).await?;
-                    self.api
-                        .write_assume_empty(audacity::command::SplitDelete)
-                        .await?;
-                    self.api
-                        .set_label(
-                            self.i - 1,
-                            None::<&str>,
-                            None,
-                            Some(*label_delete.end()),
-                            None,
-                        )
-                        .await?;
+                    let edit = Edit::Join {
+                        i: self.i,
+                        removed: LabelData::from(&self.labels[self.i]),
+                        old_end: *self.labels[self.i - 1].end(),
+                    };
+                    self.edit(edit, (self.i, self.last_read.clone())).await?;
+                }
+                Command::Split(at) => {
+                    let label = &self.labels[self.i];
+                    // mod-script-pipe can't read the playhead, so ask for the time instead
+                    let at = match at.map_or_else(
+                        || {
+                            Inputs::read(
+                                "where to split the label (prefix with + to be relative to its start): ",
+                                None,
+                            )
+                            .parse::<SplitAt>()
+                        },
+                        Ok,
+                    ) {
+                        Ok(at) => at.resolve(label),
+                        Err(err) => {
+                            println!("{err}");
+                            return Ok(());
+                        }
+                    };
+                    match split_edit(&self.labels, self.i, at) {
+                        Some(edit) => self.edit(edit, (self.i, self.last_read.clone())).await?,
+                        None => println!("{at:?} isn't inside the current label"),
+                    }
+                }
+                Command::Delete => {
+                    let edit = Edit::Delete {
+                        i: self.i,
+                        removed: LabelData::from(&self.labels[self.i]),
+                    };
+                    self.edit(edit, (self.i, self.last_read.clone())).await?;
+                }
+                Command::Undo => {
+                    let current = (self.i, self.last_read.clone());
+                    match self.history.undo(self.backend, current).await? {
+                        Some(before) => (self.i, self.last_read) = before,
+                        None => println!("nothing to undo"),
+                    }
+                    self.labels = self.backend.labels().await?;
+                }
+                Command::Redo => {
+                    let current = (self.i, self.last_read.clone());
+                    match self.history.redo(self.backend, current).await? {
+                        Some(after) => (self.i, self.last_read) = after,
+                        None => println!("nothing to redo"),
+                    }
+                    self.labels = self.backend.labels().await?;
+                }
+                Command::Back => match self.i.checked_sub(1) {
+                    Some(i) => self.goto(i),
+                    None => println!("already at the first label"),
+                },
+                Command::Goto(i) => {
+                    if i < self.labels.len() {
+                        self.goto(i);
+                    } else {
+                        println!("there are only {} labels", self.labels.len());
+                    }
+                }
+                Command::Skip => {
+                    // keep the last read chapter, when the skipped label isn't named yet
+                    self.last_read = read_from(&self.labels[self.i]).or(self.last_read.take());
+                    self.i += 1;
+                }
+                Command::List => println!("{}", self.listing()),
+                Command::Play => {
+                    let label = &self.labels[self.i];
+                    let (start, end) = (*label.start(), *label.end());
+                    self.play(start, end).await;
+                }
+                Command::PlayStart(len) => {
+                    let label = &self.labels[self.i];
+                    let (start, end) = (*label.start(), *label.end());
+                    self.play(start, end.min(start + len.unwrap_or(PREVIEW_LENGTH)))
+                        .await;
+                }
+                Command::PlayEnd(len) => {
+                    let label = &self.labels[self.i];
+                    let (start, end) = (*label.start(), *label.end());
+                    self.play(
+                        start.max(end.saturating_sub(len.unwrap_or(PREVIEW_LENGTH))),
+                        end,
+                    )
+                    .await;
                 }
             }
             Ok(())
         }
-    }
-
-    pub async fn adjust_labels(
-        audacity: &mut AudacityApi,
-    ) -> Result<(), audacity::ConnectionError> {
-        let labels = audacity.get_label_info().await?; // get new labels
-
-        for element in labels.values().flatten().open_border_pairs() {
-            zoom_to_label(audacity, element).await?;
 
-            let _ = Inputs::read(
-                "Dr\u{fc}ck Enter, wenn du bereit f\u{fc}r den n\u{e4}chsten Schritt bist",
-                None,
-            );
+        /// plays `start..end`, a failure like a missing player is only reported
+        async fn play(&mut self, start: Duration, end: Duration) {
+            if let Err(err) = self.backend.play(start, end).await {
+                println!("couldn't play the label, because {err}");
+            }
         }
-        Ok(())
     }
+
     async fn zoom_to_label(
-        api: &mut audacity::AudacityApi,
+        backend: &mut dyn Backend,
         label: State<&TimeLabel>,
-    ) -> Result<(), audacity::ConnectionError> {
+    ) -> Result<(), Error> {
         let (prev_end, next_start) = match label {
             State::Start(a) => (a.start(), *a.start() + Duration::from_secs(10)),
             State::Middle(a, b) => (a.end(), *b.start()),
             State::End(b) => (b.end(), *b.end() + Duration::from_secs(10)),
         };
-        api.zoom_to(
-            audacity::data::Selection::Part {
-                start: *prev_end - Duration::from_secs(10),
-                end: next_start + Duration::from_secs(10),
-                relative_to: audacity::data::RelativeTo::ProjectStart,
-            },
-            audacity::data::Save::Discard,
-        )
-        .await
+        backend
+            .zoom_to(
+                prev_end.saturating_sub(Duration::from_secs(10)),
+                next_start + Duration::from_secs(10),
+            )
+            .await
     }
-}
 
-#[derive(Debug, Error)]
-#[error("couldn't move file {file:?} to {dst:?}, with reason \"{source}\"")]
-pub struct MoveError {
-    file: PathBuf,
-    dst: PathBuf,
-    source: common::io::MoveError,
-}
-async fn move_results(
-    patterns: impl Iterator<Item = &TaggedFile> + Send,
-    from: impl AsRef<Path> + Send + Sync,
-    to: impl AsRef<Path> + Send + Sync,
-    args: &Arguments,
-) -> Result<(), MoveError> {
-    patterns
-        .map(|tag| {
-            let mut dst = to.as_ref().to_path_buf();
-            let mut file = from.as_ref().to_path_buf();
-            let name = build_timelabel_name::<OsStr, &str, &str>(
-                tag.get::<Album>(),
-                &(tag.get::<Track>().unwrap() as usize).into(),
-                None,
-                tag.get::<Title>(),
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::worker::test_util::{files_backend, test_dir, write_labels};
+
+        fn secs(secs: u64) -> Duration {
+            Duration::from_secs(secs)
+        }
+        fn names(labels: &[TimeLabel]) -> Vec<&str> {
+            labels
+                .iter()
+                .map(|label| label.name().unwrap_or_default())
+                .collect_vec()
+        }
+
+        #[tokio::test]
+        #[ignore = "user input test"]
+        async fn full_ac_test() {
+            let mut m_index =
+                MultiIndex::new("/home/nilsj/Musik/newly ripped/Aufnahmen/current".into()).await;
+            let ac =
+                FullNameCompleter::new(&mut m_index, common::str::filter::Levenshtein::new(true));
+            let res =
+                common::args::input::Inputs::read_with_suggestion("enter a chapter:", None, ac);
+            println!("read {res:?}");
+        }
+
+        fn unnamed_labels() -> [TimeLabel; 3] {
+            [(0, 10), (15, 20), (25, 30)]
+                .map(|(start, end)| TimeLabel::new::<&str>(secs(start), secs(end), None))
+        }
+        fn plan() -> Plan {
+            Plan::from_toml_str(
+                r#"
+                [[label]]
+                index = 0
+                series = "A"
+                chapter = 1
+                title = "X"
+                [[label]]
+                index = 1
+                series = "A"
+                chapter = 1
+                title = "X"
+                [[label]]
+                index = 2
+                series = "A"
+                chapter = 2
+                title = "Y"
+                "#,
+            )
+            .unwrap()
+        }
+
+        #[tokio::test]
+        async fn planned_names() {
+            let dir = test_dir("planned_names");
+            let mut backend = files_backend(&dir, unnamed_labels()).await;
+
+            let mut m_index = MultiIndex::new(dir).await;
+            FancyNamer::new(&mut backend, &mut m_index, Some(plan()), None)
+                .await
+                .unwrap()
+                .rename()
+                .await
+                .unwrap();
+
+            assert_eq!(
+                vec!["A 1.1 X", "A 1.2 X", "A 2.1 Y"],
+                names(&backend.labels().await.unwrap())
             );
-            if let Some(series) = tag.get::<Album>() {
-                let (main, sub) = series
-                    .split_once(MultiIndex::SUBSERIES_DELIMENITER)
-                    .map_or_else(|| (series, None), |(main, sub)| (main, Some(sub)));
-                dst.push(main);
-                if let Some(sub) = sub {
-                    dst.push(sub);
-                }
+        }
+
+        #[cfg(unix)]
+        #[tokio::test]
+        async fn planned_names_with_audacity() {
+            let dir = test_dir("planned_names_with_audacity");
+            let server = crate::worker::backend::mock::MockServer::start(&dir).unwrap();
+            let label_path = dir.join("labels.txt");
+            write_labels(unnamed_labels(), &label_path);
+            let mut backend = server.connect().await.unwrap();
+            backend
+                .load(std::path::Path::new("res/id3test.mp3"), &label_path)
+                .await
+                .unwrap();
+
+            let mut m_index = MultiIndex::new(dir).await;
+            FancyNamer::new(&mut backend, &mut m_index, Some(plan()), None)
+                .await
+                .unwrap()
+                .rename()
+                .await
+                .unwrap();
+
+            assert_eq!(
+                vec![vec![
+                    (0.0, 10.0, "A 1.1 X"),
+                    (15.0, 20.0, "A 1.2 X"),
+                    (25.0, 30.0, "A 2.1 Y")
+                ]],
+                server.project().labels()
+            );
+        }
+
+        #[tokio::test]
+        async fn back_shows_new_name() {
+            let dir = test_dir("back_shows_new_name");
+            let mut backend = files_backend(&dir, unnamed_labels()).await;
+            let mut m_index = MultiIndex::new(dir).await;
+            let mut namer = FancyNamer::new(&mut backend, &mut m_index, None, None)
+                .await
+                .unwrap();
+
+            namer
+                .edit(namer.rename_edit("A 1.1 X".to_owned()), (0, None))
+                .await
+                .unwrap();
+            namer.i += 1;
+            namer.run_command(Command::Back).await.unwrap();
+            assert_eq!(0, namer.i, "didn't go back");
+            assert_eq!(
+                Some("A 1.1 X".to_owned()),
+                namer.initial(),
+                "new name isn't offered"
+            );
+            assert_eq!(
+                Some(">   0 0:00:00 - 0:00:10 A 1.1 X"),
+                namer.listing().lines().next(),
+                "new name isn't listed"
+            );
+        }
+
+        #[tokio::test]
+        async fn reload_forgets_history() {
+            let dir = test_dir("reload_forgets_history");
+            let mut backend = files_backend(&dir, unnamed_labels()).await;
+            let mut m_index = MultiIndex::new(dir).await;
+            let mut namer = FancyNamer::new(&mut backend, &mut m_index, None, None)
+                .await
+                .unwrap();
+
+            namer
+                .edit(namer.rename_edit("A 1.1 X".to_owned()), (0, None))
+                .await
+                .unwrap();
+            namer.i += 1;
+            namer.run_command(Command::ReloadLabel).await.unwrap();
+            namer.run_command(Command::Undo).await.unwrap();
+            assert_eq!(1, namer.i, "state from before the reload restored");
+            assert_eq!(
+                vec!["A 1.1 X", "", ""],
+                names(&namer.labels),
+                "edit from before the reload undone"
+            );
+        }
+
+        #[tokio::test]
+        async fn play_failure_continues() {
+            let dir = test_dir("play_failure_continues");
+            // the recording doesn't exist, so playing it fails
+            let mut backend = files_backend(&dir, unnamed_labels()).await;
+            let mut m_index = MultiIndex::new(dir).await;
+            let mut namer = FancyNamer::new(&mut backend, &mut m_index, None, None)
+                .await
+                .unwrap();
+            for command in [
+                Command::Play,
+                Command::PlayStart(None),
+                Command::PlayEnd(None),
+            ] {
+                assert!(
+                    namer.run_command(command).await.is_ok(),
+                    "failed playing ends naming"
+                );
             }
-            file.push(name);
-            file.set_extension(tag.ext());
+        }
 
-            common::io::move_file(file, dst, args.dry_run())
-                .map_err(move |(source, file, dst)| MoveError { file, dst, source })
-        })
-        .join_all()
-        .await
-        .into_iter()
-        .collect::<Result<(), _>>()
-}
+        #[tokio::test]
+        async fn split_renumbers_parts() {
+            let mut backend = files_backend(
+                &test_dir("split_renumbers_parts"),
+                [(0, 10, "A 1.1 X"), (15, 20, "A 1.2 X"), (25, 30, "A 2.1 Y")]
+                    .map(|(start, end, name)| TimeLabel::new(secs(start), secs(end), Some(name))),
+            )
+            .await;
+            let labels = backend.labels().await.unwrap();
+            assert!(
+                split_edit(&labels, 0, secs(10)).is_none(),
+                "split at the end"
+            );
 
-async fn merge_parts<'a>(
-    args: &Arguments,
-    audacity: &mut audacity::AudacityApi,
-    m_index: &mut MultiIndex<'a>,
-    hint: audacity::data::TrackHint,
-) -> Result<Vec<TaggedFile>, audacity::ConnectionError> {
-    let label_track_nr = hint
-        .get_label_track_nr(audacity)
-        .await?
-        .expect("no track found");
-    let labels = audacity
-        .get_label_info()
-        .await?
-        .remove(&label_track_nr)
-        .unwrap_or_else(|| panic!("couldn't get track with number {label_track_nr}"));
-    audacity.select_tracks(std::iter::once(1)).await?;
-    audacity
-        .write_assume_empty(audacity::command::RemoveTracks)
-        .await?;
-    let grouped_labels = labels.iter().into_group_map_by(|label| {
-        let Some((series, nr, _, chapter)) =
-            crate::archive::data::Archive::parse_line(label.name().unwrap())
-        else {
-            panic!("couldn't parse {:?}", label.name().unwrap());
-        };
-        (series, nr, chapter)
-    });
-    let hint =
-        audacity::data::TrackHint::TrackNr(audacity.add_label_track(Some("merged")).await?).into();
-    for (group, labels) in grouped_labels.iter().filter(|(_, value)| value.len() > 1) {
-        let mut name = format!("{} {}", group.0, group.1);
-        if let Some(chapter) = group.2 {
-            let _ = write!(name, " {chapter}");
+            split_edit(&labels, 0, secs(4))
+                .unwrap()
+                .apply(&mut backend)
+                .await
+                .unwrap();
+            assert_eq!(
+                vec![
+                    (secs(0), secs(4), "A 1.1 X"),
+                    (secs(4), secs(10), "A 1.2 X"),
+                    (secs(15), secs(20), "A 1.3 X"),
+                    (secs(25), secs(30), "A 2.1 Y")
+                ],
+                backend
+                    .labels()
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|label| (*label.start(), *label.end(), label.name().unwrap()))
+                    .collect_vec()
+            );
         }
-        let _ = audacity
-            .add_label(
-                audacity::data::TimeLabel::new::<String>(
-                    *labels.first().unwrap().start(),
-                    *labels.last().unwrap().end(),
-                    Some(name),
-                ),
-                Some(hint),
+
+        #[tokio::test]
+        async fn suggest_next_chapter() {
+            let dir = test_dir("suggest_next_chapter");
+            std::fs::create_dir(dir.join("A")).unwrap();
+            std::fs::write(
+                dir.join("A").join("index.toml"),
+                r#"chapters.main = [["X", 2000], ["Y", 2000], ["Z", 2005]]"#,
             )
-            .await?;
+            .unwrap();
+            let mut backend = files_backend(
+                &dir,
+                ["A 1.1 X", "A 1.2 X", "A 2.1 Y", "A 2.2 Y", "Segment 5"]
+                    .into_iter()
+                    .zip(0..)
+                    .map(|(name, i)| TimeLabel::new(secs(i * 10), secs(i * 10 + 5), Some(name))),
+            )
+            .await;
+            let mut m_index = MultiIndex::new(dir).await;
+            let mut namer = FancyNamer::new(&mut backend, &mut m_index, None, None)
+                .await
+                .unwrap();
+
+            namer.goto(3);
+            assert_eq!(Some("A 2".to_owned()), namer.suggestion(), "next part");
+            namer.goto(4);
+            assert_eq!(Some("A 3".to_owned()), namer.suggestion(), "next chapter");
+            namer.air_date = NaiveDate::from_ymd_opt(2003, 1, 1);
+            assert_eq!(
+                Some("A ".to_owned()),
+                namer.suggestion(),
+                "next chapter is released after the recording"
+            );
+        }
+
+        #[tokio::test]
+        async fn suggest_from_renamed_labels() {
+            let dir = test_dir("suggest_from_renamed_labels");
+            let mut backend = files_backend(
+                &dir,
+                (0..6).map(|i| TimeLabel::new::<&str>(secs(i * 10), secs(i * 10 + 5), None)),
+            )
+            .await;
+            let mut m_index = MultiIndex::new(dir).await;
+            let mut namer = FancyNamer::new(&mut backend, &mut m_index, None, None)
+                .await
+                .unwrap();
+
+            for name in ["A 1.1 X", "A 1.2 X", "A 2.1 Y", "A 2.2 Y"] {
+                let before = (namer.i, namer.last_read.clone());
+                namer
+                    .edit(namer.rename_edit(name.to_owned()), before)
+                    .await
+                    .unwrap();
+                namer.i += 1;
+            }
+            namer.goto(4);
+            assert_eq!(2, namer.expected_parts(), "parts of the renamed chapters");
+            assert_eq!(Some("A 3".to_owned()), namer.suggestion(), "next chapter");
+        }
+
+        #[test]
+        fn parse_commands() {
+            assert_eq!(Ok(Command::List), "list".parse());
+            assert_eq!(Ok(Command::Goto(3)), "goto 3".parse());
+            assert_eq!(Ok(Command::Goto(12)), " goto  12 ".parse());
+            assert!("goto".parse::<Command>().is_err());
+            assert!("goto x".parse::<Command>().is_err());
+            assert!("back 2".parse::<Command>().is_err());
+            assert_eq!(
+                Ok(Command::Index(PathBuf::from("some dir/index.toml"))),
+                "index some dir/index.toml".parse()
+            );
+            assert_eq!(Ok(Command::PlayEnd(None)), "play-end".parse());
+            assert_eq!(
+                Ok(Command::PlayStart(Some(Duration::from_secs(90)))),
+                "play-start 1m30s".parse()
+            );
+        }
     }
-    audacity
-        .write_assume_empty(audacity::command::SelAllTracks)
-        .await?;
-    for (_group, labels) in grouped_labels
-        .iter()
-        .sorted_by(|(g_a, _), (g_b, _)| Ord::cmp(g_b, g_a))
-    {
-        for (b, a) in labels.iter().rev().tuple_windows() {
-            audacity
-                .select(audacity::data::Selection::Part {
-                    start: *a.end(),
-                    end: *b.start(),
-                    relative_to: audacity::data::RelativeTo::ProjectStart,
-                })
-                .await?;
+}
 
-            audacity
-                .write_assume_empty(audacity::command::Delete)
-                .await?;
+fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// moves the chapters of `plan` to their destination, prints what happened to each of them
+/// and returns where they ended up
+async fn move_results<'p, 'l: 'p>(
+    plan: impl Iterator<Item = &'p PlannedChapter<'l>> + Send,
+    args: &Arguments,
+    progress: &mut progress::Progress,
+    audio_path: &Path,
+) -> Result<Vec<(&'p Path, mover::Outcome)>, Error> {
+    let mut results = Vec::new();
+    for chapter in plan {
+        let result = mover::move_file(
+            &chapter.file,
+            &chapter.dst,
+            args.on_collision(),
+            args.dry_run(),
+        )
+        .await;
+        // stored right away, so a resumed run knows where the chapter went
+        if let Some(output) = result.as_ref().ok().and_then(mover::Outcome::output) {
+            progress.add_output(audio_path, output.to_owned()).await?;
         }
+        results.push((chapter.file.as_path(), result));
     }
-    let (keys, values) = grouped_labels.into_iter().unzip::<_, _, Vec<_>, Vec<_>>();
-    let offsets = keys
+    mover::print_summary(results.iter().map(|(file, result)| (*file, result)));
+    results
         .into_iter()
-        .zip(calc_merged_offsets(values))
-        .collect::<HashMap<_, _>>();
-    let mut tags = Vec::new();
-    for ((series, chapter_number, chapter_name), offsets) in offsets {
-        let chapter_name = chapter_name.unwrap();
-
-        let mut path = args.tmp_path().to_path_buf();
-        path.push(build_timelabel_name::<OsStr, _, _>(
-            series,
-            &chapter_number,
-            None,
-            chapter_name,
-        ));
-        path.set_extension(args.export_ext());
-        let tag = tags.push_return(TaggedFile::new_empty(path).unwrap());
-
-        tag.set::<Title>(chapter_name);
-        tag.set::<Album>(series.as_ref());
-        tag.set::<Genre>(args.genre());
-        tag.set::<Track>(chapter_number.nr as u32);
+        .map(|(file, result)| Ok((file, result?)))
+        .collect()
+}
 
-        if let Ok(index) = m_index.get_index(OsString::from(series)).await {
-            let entry = index.get(chapter_number);
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BadLabelKind {
+    #[error("has no name")]
+    Unnamed,
+    #[error("couldn't be parsed")]
+    Unparsable,
+    #[error("has no chapter name")]
+    NoChapterName,
+}
+/// a label, that can't be merged
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("label {i} {name:?} at {start:?} {kind}")]
+pub struct BadLabel {
+    i: usize,
+    name: Option<String>,
+    start: Duration,
+    kind: BadLabelKind,
+}
 
-            tag.set::<TotalTracks>(index.main_len() as u32);
-            if let Some(artist) = entry.artist {
-                tag.set::<Artist>(artist.as_ref());
+type ChapterKey<'l> = (&'l str, ChapterNumber, &'l str);
+/// groups `labels` by their chapter, skipping comments starting with '#'.
+/// Fails with every label, that can't be merged
+fn group_labels(
+    labels: &[audacity::data::TimeLabel],
+) -> Result<HashMap<ChapterKey<'_>, Vec<&audacity::data::TimeLabel>>, Error> {
+    let mut grouped = HashMap::<_, Vec<_>>::new();
+    let mut bad = Vec::new();
+    for (i, label) in labels.iter().enumerate() {
+        let kind = match label.name().filter(|name| !name.is_empty()) {
+            None => BadLabelKind::Unnamed,
+            Some(name) if name.starts_with('#') => {
+                log::debug!("skipping {name:?}");
+                continue;
             }
-            match entry.release {
-                Some(
-                    index::DateOrYear::Year(year)
-                    | index::DateOrYear::Date(Datetime {
-                        date: Some(Date { year, .. }),
-                        ..
-                    }),
-                ) => tag.set::<Year>(year as i32),
-                Some(index::DateOrYear::Date(Datetime { date: None, .. })) => {
-                    log::warn!("release didn't have a date");
+            Some(name) => match crate::archive::data::Archive::parse_line(name) {
+                Some((series, nr, _, Some(chapter))) => {
+                    grouped
+                        .entry((series, nr, chapter))
+                        .or_default()
+                        .push(label);
+                    continue;
                 }
-                None => {}
-            }
+                Some((_, _, _, None)) => BadLabelKind::NoChapterName,
+                None => BadLabelKind::Unparsable,
+            },
+        };
+        bad.push(BadLabel {
+            i,
+            name: label.name().map(ToOwned::to_owned),
+            start: *label.start(),
+            kind,
+        });
+    }
+    if bad.is_empty() {
+        Ok(grouped)
+    } else {
+        Err(Error::BadLabels(bad))
+    }
+}
+
+/// the export of one chapter, computed before the project is changed
+#[derive(Debug)]
+struct PlannedChapter<'l> {
+    series: &'l str,
+    nr: ChapterNumber,
+    title: &'l str,
+    labels: Vec<&'l audacity::data::TimeLabel>,
+    /// the starts of the parts in the merged file, except the first
+    offsets: Vec<Duration>,
+    /// where the merged chapter is exported to
+    file: PathBuf,
+    /// where the exported file is moved to
+    dst: PathBuf,
+    total_tracks: Option<u32>,
+    artist: Option<String>,
+    year: Option<i32>,
+}
+impl PlannedChapter<'_> {
+    fn start(&self) -> Duration {
+        *self.labels.first().unwrap().start()
+    }
+    fn end(&self) -> Duration {
+        *self.labels.last().unwrap().end()
+    }
+    fn tag(&self, genre: &str) -> Result<TaggedFile, Error> {
+        let mut tag = TaggedFile::new_empty(self.file.clone())
+            .map_err(|err| Error::Tag(self.file.clone(), err))?;
+        tag.set::<Title>(self.title);
+        tag.set::<Album>(self.series);
+        tag.set::<Genre>(genre);
+        tag.set::<Track>(self.nr.nr as u32);
+        if let Some(total_tracks) = self.total_tracks {
+            tag.set::<TotalTracks>(total_tracks);
+        }
+        if let Some(artist) = self.artist.as_deref() {
+            tag.set::<Artist>(artist);
+        }
+        if let Some(year) = self.year {
+            tag.set::<Year>(year);
         }
-        if !offsets.is_empty() {
+        if !self.offsets.is_empty() {
             // don't add only label at 0
             for (i, offset) in std::iter::once(Duration::ZERO)
-                .chain(offsets.into_iter())
+                .chain(self.offsets.iter().copied())
                 .lzip(1..)
             {
                 tag.set_chapter(i, offset, Some(&format!("Part {i}")));
             }
         }
+        Ok(tag)
     }
+}
+
+/// computes the chapters of `labels` and where they will be exported, without changing anything
+async fn plan_export<'l>(
+    args: &Arguments,
+    work_dir: &Path,
+    labels: &'l [audacity::data::TimeLabel],
+    m_index: &mut MultiIndex<'_>,
+) -> Result<Vec<PlannedChapter<'l>>, Error> {
+    let (keys, values) = group_labels(labels)?
+        .into_iter()
+        .sorted_by_key(|(_, labels)| *labels[0].start())
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+    let offsets = calc_merged_offsets(values.iter().map(|labels| labels.iter().copied()));
+    let to = args.output_dir().unwrap_or(work_dir);
+    let template = args.output_template().parse::<template::Template>()?;
+
+    let mut plan = Vec::new();
+    for (((series, nr, title), labels), offsets) in keys.into_iter().zip(values).zip(offsets) {
+        // the same name the backends export the merged label to
+        let mut file_name = OsString::from(template::sanitize(&build_timelabel_name::<str, _, _>(
+            series, &nr, None, title,
+        )));
+        // push extension manually, like the backends, because the title might contain a '.'
+        file_name.push(".");
+        file_name.push(args.export_ext());
+        let file = work_dir.join(file_name);
+        let mut chapter = PlannedChapter {
+            series,
+            nr,
+            title,
+            labels,
+            offsets,
+            file,
+            dst: PathBuf::new(),
+            total_tracks: None,
+            artist: None,
+            year: None,
+        };
+
+        if let Ok(index) = m_index.get_index(OsString::from(series)).await {
+            chapter.total_tracks = Some(index.main_len() as u32);
+            if let Some(entry) = index.try_get(nr) {
+                chapter.artist = entry.artist.map(Cow::into_owned);
+                match entry.release {
+                    Some(
+                        index::DateOrYear::Year(year)
+                        | index::DateOrYear::Date(Datetime {
+                            date: Some(Date { year, .. }),
+                            ..
+                        }),
+                    ) => chapter.year = Some(year as i32),
+                    Some(index::DateOrYear::Date(Datetime { date: None, .. })) => {
+                        log::warn!("release didn't have a date");
+                    }
+                    None => {}
+                }
+            }
+        }
+        chapter.dst = to.join(template.render(&template::Values {
+            series,
+            nr,
+            title,
+            artist: chapter.artist.as_deref(),
+            year: chapter.year,
+            ext: args.export_ext(),
+        }));
+        plan.push(chapter);
+    }
+    Ok(plan)
+}
+
+fn print_plan(plan: &[PlannedChapter<'_>]) {
+    println!(
+        "{:<50} {:>5} {:>8} {:>8}  destination",
+        "file", "parts", "start", "end"
+    );
+    for chapter in plan {
+        println!(
+            "{:<50} {:>5} {:>8} {:>8}  {}",
+            chapter.file.file_name().unwrap().to_string_lossy(),
+            chapter.labels.len(),
+            format_time(chapter.start()),
+            format_time(chapter.end()),
+            chapter.dst.display()
+        );
+        if !chapter.offsets.is_empty() {
+            println!(
+                "{:<50} parts start at {}",
+                "",
+                chapter.offsets.iter().map(|it| format_time(*it)).join(", ")
+            );
+        }
+    }
+}
 
-    Ok(tags)
+/// merges the parts of each chapter in `plan` into one label and deletes the audio between them
+async fn merge_parts(backend: &mut dyn Backend, plan: &[PlannedChapter<'_>]) -> Result<(), Error> {
+    let merged = plan
+        .iter()
+        .map(|chapter| {
+            audacity::data::TimeLabel::new::<String>(
+                chapter.start(),
+                chapter.end(),
+                Some(format!(
+                    "{} {} {}",
+                    chapter.series, chapter.nr, chapter.title
+                )),
+            )
+        })
+        .collect_vec();
+    backend.replace_labels(merged, "merged").await?;
+
+    // delete from the back, so the positions of the remaining gaps don't change
+    for (start, end) in plan
+        .iter()
+        .flat_map(|chapter| {
+            chapter
+                .labels
+                .iter()
+                .tuple_windows()
+                .map(|(a, b)| (*a.end(), *b.start()))
+        })
+        .sorted()
+        .rev()
+    {
+        backend.delete_range(start, end).await?;
+    }
+    Ok(())
+}
+
+/// applies `plan`: merges, exports, tags and moves the chapters.
+/// Continues after `state`, so a resumed run neither deletes audio twice nor touches moved files
+async fn export(
+    args: &Arguments,
+    backend: &mut dyn Backend,
+    plan: &[PlannedChapter<'_>],
+    progress: &mut progress::Progress,
+    recording: &Recording,
+    state: Option<progress::State>,
+) -> Result<(), Error> {
+    use progress::State;
+    let work_dir = args.work_dir(recording.path());
+    let audio_path = &recording.joined_path(work_dir);
+    let label_path = recording.label_path();
+    let reached = |it: State| state.is_some_and(|state| state >= it);
+
+    // a reloaded project lost the merge, so it is merged again until the chapters are exported
+    if reached(State::Exported) || (reached(State::Merged) && backend.keeps_project()) {
+        log::debug!("skipping merge");
+    } else {
+        merge_parts(backend, plan).await?;
+        if !reached(State::Merged) {
+            progress
+                .reach(audio_path, &label_path, State::Merged)
+                .await?;
+        }
+    }
+
+    if reached(State::Exported) {
+        log::debug!("skipping export of the audio");
+    } else {
+        backend
+            .export_audio(work_dir, args.export_ext(), false)
+            .await?;
+        progress
+            .reach(audio_path, &label_path, State::Exported)
+            .await?;
+    }
+
+    if reached(State::Tagged) {
+        log::debug!("skipping tagging");
+    } else {
+        for chapter in plan {
+            let mut tag = chapter.tag(args.genre())?;
+            tag.reload_empty()
+                .map_err(|err| Error::Tag(tag.path().into(), err))?;
+            tag.save_changes(false)
+                .map_err(|err| Error::Tag(tag.path().into(), err))?;
+        }
+        progress
+            .reach(audio_path, &label_path, State::Tagged)
+            .await?;
+        // the chapters were exported again, so the outputs of an earlier run are outdated
+        progress.set_outputs(audio_path, Vec::new()).await?;
+    }
+
+    // files moved before an interruption are skipped, their outputs were already stored
+    let pending = plan.iter().filter(|chapter| chapter.file.exists());
+    let outcomes = move_results(pending, args, progress, audio_path).await?;
+    progress
+        .reach(audio_path, &label_path, State::Moved)
+        .await?;
+
+    if args.cleanup() {
+        // a chapter kept because of a collision is the only copy of its audio, so it stays
+        let mut intermediate = outcomes
+            .iter()
+            .filter(|(_, outcome)| {
+                matches!(
+                    outcome,
+                    mover::Outcome::Moved(_)
+                        | mover::Outcome::Suffixed(_)
+                        | mover::Outcome::Replaced(_)
+                        | mover::Outcome::Duplicate(_)
+                )
+            })
+            .map(|(file, _)| *file)
+            .collect_vec();
+        if recording.is_split() {
+            intermediate.push(audio_path);
+        }
+        for file in intermediate {
+            log::debug!("deleting {file:?}");
+            match tokio::fs::remove_file(file).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    log::warn!("couldn't delete {file:?}, because {err}");
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
 }
 
 fn calc_merged_offsets<'a, Iter>(grouped_labels: Iter) -> Vec<Vec<Duration>>
@@ -1407,6 +1923,289 @@ mod tests {
         );
     }
 
+    #[test]
+    fn group_reports_bad_labels() {
+        let secs = Duration::from_secs;
+        let labels = [
+            TimeLabel::new(secs(0), secs(5), Some("A 1.1 X")),
+            TimeLabel::new(secs(5), secs(10), Some("# jingle")),
+            TimeLabel::new(secs(10), secs(15), Some("A 1.2 X")),
+            TimeLabel::new(secs(15), secs(20), Some("A 2")),
+            TimeLabel::new::<&str>(secs(20), secs(25), None),
+            TimeLabel::new(secs(25), secs(30), Some("Segment")),
+        ];
+        let Err(Error::BadLabels(bad)) = group_labels(&labels) else {
+            panic!("expected bad labels");
+        };
+        assert_eq!(
+            vec![
+                (3, BadLabelKind::NoChapterName),
+                (4, BadLabelKind::Unnamed),
+                (5, BadLabelKind::Unparsable)
+            ],
+            bad.into_iter().map(|it| (it.i, it.kind)).collect_vec()
+        );
+
+        let grouped = group_labels(&labels[..3]).unwrap();
+        assert_eq!(1, grouped.len(), "comments should be skipped");
+        assert_eq!(2, grouped.values().next().unwrap().len());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn merge_with_audacity() {
+        let dir = test_util::test_dir("merge_with_audacity");
+        let server = backend::mock::MockServer::start(&dir).unwrap();
+        let label_path = dir.join("labels.txt");
+        let secs = Duration::from_secs;
+        test_util::write_labels(
+            [
+                TimeLabel::new(secs(10), secs(20), Some("Gruselkabinett 6.1 Das Haus")),
+                TimeLabel::new(secs(25), secs(40), Some("Gruselkabinett 6.2 Das Haus")),
+                TimeLabel::new(secs(50), secs(60), Some("Gruselkabinett 7.1 Der Turm")),
+            ],
+            &label_path,
+        );
+        let args = Arguments::from(
+            args::Config::default(),
+            <args::Parameter as clap::Parser>::parse_from([
+                PathBuf::from(""),
+                dir.join("recording.mp3"),
+            ]),
+        );
+
+        let mut backend = server.connect().await.unwrap();
+        backend
+            .load(Path::new("res/id3test.mp3"), &label_path)
+            .await
+            .unwrap();
+        let mut m_index = MultiIndex::new(dir.clone()).await;
+        let labels = backend.labels().await.unwrap();
+        let plan = plan_export(&args, &dir, &labels, &mut m_index)
+            .await
+            .unwrap();
+        merge_parts(&mut backend, &plan).await.unwrap();
+
+        assert_eq!(
+            vec![vec![
+                (10.0, 35.0, "Gruselkabinett 6 Das Haus"),
+                (45.0, 55.0, "Gruselkabinett 7 Der Turm")
+            ]],
+            server.project().labels()
+        );
+        assert_eq!(
+            vec![
+                (dir.join("Gruselkabinett 6 Das Haus.mp3"), vec![secs(10)]),
+                (dir.join("Gruselkabinett 7 Der Turm.mp3"), vec![])
+            ],
+            plan.iter()
+                .map(|chapter| (chapter.file.clone(), chapter.offsets.clone()))
+                .collect_vec()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resume_after_tagging_only_moves() {
+        let dir = test_util::test_dir("resume_after_tagging");
+        let server = backend::mock::MockServer::start(&dir).unwrap();
+        let audio_path = dir.join("recording.mp3");
+        let label_path = dir.join("recording.txt");
+        let secs = Duration::from_secs;
+        std::fs::write(&audio_path, b"").unwrap();
+        test_util::write_labels(
+            [
+                TimeLabel::new(secs(10), secs(20), Some("Gruselkabinett 6.1 Das Haus")),
+                TimeLabel::new(secs(25), secs(40), Some("Gruselkabinett 6.2 Das Haus")),
+                TimeLabel::new(secs(50), secs(60), Some("Gruselkabinett 7.1 Der Turm")),
+            ],
+            &label_path,
+        );
+        let args = Arguments::from(
+            args::Config::default(),
+            <args::Parameter as clap::Parser>::parse_from([PathBuf::from(""), audio_path.clone()]),
+        );
+
+        let mut backend = server.connect().await.unwrap();
+        backend
+            .load(Path::new("res/id3test.mp3"), &label_path)
+            .await
+            .unwrap();
+        let mut m_index = MultiIndex::new(dir.clone()).await;
+        let labels = crate::splitter::read_labels(&label_path).unwrap();
+        let plan = plan_export(&args, &dir, &labels, &mut m_index)
+            .await
+            .unwrap();
+        assert_eq!(2, plan.len(), "wrong number of chapters");
+        // the first chapter was moved next to a different version before the interruption
+        let suffixed = plan[0].dst.with_file_name("suffixed.mp3");
+        std::fs::create_dir_all(plan[0].dst.parent().unwrap()).unwrap();
+        std::fs::write(&plan[0].dst, b"").unwrap();
+        std::fs::write(&suffixed, b"").unwrap();
+        std::fs::write(&plan[1].file, b"").unwrap();
+
+        let mut progress = progress::Progress::read_in(&dir).await.unwrap();
+        progress
+            .reach(&audio_path, &label_path, progress::State::Tagged)
+            .await
+            .unwrap();
+        progress
+            .add_output(&audio_path, suffixed.clone())
+            .await
+            .unwrap();
+        export(
+            &args,
+            &mut backend,
+            &plan,
+            &mut progress,
+            &Recording::group([audio_path.clone()]).remove(0),
+            Some(progress::State::Tagged),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            3,
+            server.project().labels()[0].len(),
+            "merged again after resume"
+        );
+        assert!(
+            plan.iter()
+                .all(|chapter| !chapter.file.exists() && chapter.dst.exists()),
+            "not everything was moved"
+        );
+        assert_eq!(Some(progress::State::Moved), progress.get(&audio_path));
+        assert_eq!(
+            vec![suffixed, plan[1].dst.clone()],
+            progress.record(&audio_path).unwrap().outputs
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn run_names_with_plan() {
+        let dir = test_util::test_dir("run_names_with_plan");
+        let server = backend::mock::MockServer::start(&dir).unwrap();
+        let audio_path = dir.join("recording.mp3");
+        std::fs::copy("res/id3test.mp3", &audio_path).unwrap();
+        let secs = Duration::from_secs;
+        test_util::write_labels(
+            [(0, 10), (15, 20), (25, 30)]
+                .map(|(start, end)| TimeLabel::new::<&str>(secs(start), secs(end), None)),
+            &audio_path.with_extension("txt"),
+        );
+        let plan_path = dir.join("plan.toml");
+        std::fs::write(
+            &plan_path,
+            r#"
+            [[label]]
+            index = 0
+            series = "A"
+            chapter = 1
+            title = "X"
+            [[label]]
+            index = 1
+            series = "A"
+            chapter = 1
+            title = "X"
+            [[label]]
+            index = 2
+            series = "A"
+            chapter = 2
+            title = "Y"
+            "#,
+        )
+        .unwrap();
+        let args = Arguments::from(
+            args::Config::default(),
+            <args::Parameter as clap::Parser>::parse_from([
+                OsString::new(),
+                audio_path.clone().into(),
+                "--plan".into(),
+                plan_path.into(),
+                "--dry-run".into(),
+            ]),
+        );
+
+        let mut backend = server.connect().await.unwrap();
+        run_recordings(&args, &mut backend).await.unwrap();
+
+        let project = server.project();
+        assert_eq!(
+            vec!["A 1.1 X", "A 1.2 X", "A 2.1 Y"],
+            project
+                .params("SetLabel")
+                .iter()
+                .filter_map(|it| it.get("Text").map(String::as_str))
+                .collect_vec(),
+            "planned labels weren't named"
+        );
+        assert!(
+            !project.command_names().contains(&"Export2"),
+            "exported in a dry run"
+        );
+        assert_eq!(Some(&"Exit"), project.command_names().last(), "not closed");
+        drop(project);
+        let progress = progress::Progress::read_in(&dir).await.unwrap();
+        assert_eq!(
+            None,
+            progress.get(&audio_path),
+            "progress saved in a dry run"
+        );
+    }
+
+    #[tokio::test]
+    async fn files_backend_rejects_opus() {
+        let args = Arguments::from(
+            args::Config::default(),
+            <args::Parameter as clap::Parser>::parse_from([
+                "",
+                "--backend",
+                "files",
+                "--export-ext",
+                "opus",
+                "recording.mp3",
+            ]),
+        );
+        assert!(
+            matches!(run(&args).await, Err(Error::UnsupportedFormat(ext)) if ext == "opus"),
+            "opus accepted without audacity"
+        );
+    }
+
+    #[tokio::test]
+    async fn reset_split_by_part() {
+        let dir = test_util::test_dir("reset_split_by_part");
+        let joined = dir.join("rec (joined).mp3");
+        std::fs::write(&joined, b"audio").unwrap();
+        let mut progress = progress::Progress::read_in(&dir).await.unwrap();
+        progress
+            .reach(&joined, &dir.join("rec.txt"), progress::State::Named)
+            .await
+            .unwrap();
+        progress
+            .set_parts(&joined, &[dir.join("rec (1).mp3"), dir.join("rec (2).mp3")])
+            .await
+            .unwrap();
+
+        reset(
+            &dir,
+            &dir.join("rec (2).mp3"),
+            Some(progress::State::Loaded),
+            false,
+        )
+        .await
+        .unwrap();
+        let progress = progress::Progress::read_in(&dir).await.unwrap();
+        assert_eq!(Some(progress::State::Loaded), progress.get(&joined));
+
+        reset(&dir, &dir.join("rec.mp3"), None, false)
+            .await
+            .unwrap();
+        let progress = progress::Progress::read_in(&dir).await.unwrap();
+        assert_eq!(None, progress.get(&joined), "not forgotten");
+    }
+
     #[ignore = "needs user input"]
     #[tokio::test]
     async fn test_chapter_completer() {
@@ -0,0 +1,365 @@
+//! moves the exported chapters to their destination, without losing a file on the way
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::Hasher,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use super::args::Collision;
+use crate::splitter::frame::Mp3Stream;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("couldn't move {file:?} to {dst:?}, because {source}")]
+    IO {
+        file: PathBuf,
+        dst: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("the copy of {file:?} at {dst:?} differs from it, kept the original")]
+    Verify { file: PathBuf, dst: PathBuf },
+}
+
+/// recordings, whose durations differ less than this, are considered the same episode
+const SAME_DURATION: Duration = Duration::from_secs(5);
+
+/// what happened to one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Moved(PathBuf),
+    /// moved next to an existing, different file
+    Suffixed(PathBuf),
+    /// replaced an existing file
+    Replaced(PathBuf),
+    /// the existing file has the same audio, so the new one was deleted
+    Duplicate(PathBuf),
+    /// the existing file was kept, the new one stays where it is
+    Kept(PathBuf),
+    /// nothing was changed, because of a dry run, but this would have happened
+    DryRun(Box<Outcome>),
+}
+impl Outcome {
+    /// the file, that contains the chapter now
+    #[must_use]
+    pub fn output(&self) -> Option<&Path> {
+        match self {
+            Self::Moved(path)
+            | Self::Suffixed(path)
+            | Self::Replaced(path)
+            | Self::Duplicate(path)
+            | Self::Kept(path) => Some(path),
+            Self::DryRun(_) => None,
+        }
+    }
+}
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Moved(path) => write!(f, "moved to {path:?}"),
+            Self::Suffixed(path) => write!(f, "moved to {path:?}, next to a different version"),
+            Self::Replaced(path) => write!(f, "replaced {path:?}"),
+            Self::Duplicate(path) => write!(f, "deleted, same audio as {path:?}"),
+            Self::Kept(path) => write!(f, "not moved, {path:?} already exists"),
+            Self::DryRun(outcome) => write!(f, "dry run, {outcome}"),
+        }
+    }
+}
+
+/// the length and average bitrate in kbit/s of a mp3 file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quality {
+    duration: Duration,
+    bitrate: f64,
+}
+
+/// the audio of a file, ignoring the tags of mp3 files.
+/// Only mp3 files are parsed, others like opus are compared by their whole content,
+/// so retagged copies aren't found and [`Collision::Best`] can't choose by [`Quality`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AudioInfo {
+    quality: Option<Quality>,
+    hash: u64,
+}
+impl AudioInfo {
+    /// reads `path` on a blocking thread, because it reads the whole file
+    async fn read(path: &Path) -> Option<Self> {
+        let path = path.to_owned();
+        tokio::task::spawn_blocking(move || Self::read_blocking(&path))
+            .await
+            .ok()?
+    }
+    fn read_blocking(path: &Path) -> Option<Self> {
+        let Ok(stream) = Mp3Stream::read(path) else {
+            return Some(Self {
+                quality: None,
+                hash: hash_data(&std::fs::read(path).ok()?),
+            });
+        };
+        let mut hasher = DefaultHasher::new();
+        let mut weighted_bitrate = 0.0;
+        for frame in stream.frames() {
+            if frame.is_info_frame(stream.data()) {
+                continue;
+            }
+            hasher.write(frame.data(stream.data()));
+            weighted_bitrate +=
+                f64::from(frame.header.bitrate) * frame.header.duration().as_secs_f64();
+        }
+        let duration = stream.duration();
+        Some(Self {
+            quality: Some(Quality {
+                duration,
+                bitrate: weighted_bitrate / duration.as_secs_f64().max(f64::EPSILON),
+            }),
+            hash: hasher.finish(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    Move,
+    Suffix,
+    Replace,
+    Duplicate,
+    Keep,
+}
+
+/// what to do with `new`, when its destination contains `existing`
+fn decide(new: Option<AudioInfo>, existing: Option<AudioInfo>, collision: Collision) -> Decision {
+    if let (Some(new), Some(existing)) = (new, existing) {
+        if new.hash == existing.hash {
+            return Decision::Duplicate;
+        }
+    }
+    match collision {
+        Collision::Skip => Decision::Keep,
+        Collision::Overwrite => Decision::Replace,
+        Collision::Suffix => Decision::Suffix,
+        Collision::Best => match (
+            new.and_then(|it| it.quality),
+            existing.and_then(|it| it.quality),
+        ) {
+            (Some(new), Some(existing))
+                if new.duration.abs_diff(existing.duration) <= SAME_DURATION =>
+            {
+                if new.bitrate > existing.bitrate {
+                    Decision::Replace
+                } else {
+                    Decision::Keep
+                }
+            }
+            // different lengths or unknown quality, so keep both
+            _ => Decision::Suffix,
+        },
+    }
+}
+
+/// `dst` with the first free number like " (1)" added to its name
+fn with_free_suffix(dst: &Path) -> PathBuf {
+    (1..)
+        .map(|i| {
+            let mut file_name = dst.file_stem().unwrap_or_default().to_owned();
+            file_name.push(format!(" ({i})"));
+            if let Some(ext) = dst.extension() {
+                file_name.push(".");
+                file_name.push(ext);
+            }
+            dst.with_file_name(file_name)
+        })
+        .find(|it| !it.exists())
+        .expect("some number is free")
+}
+
+fn hash_data(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(data);
+    hasher.finish()
+}
+async fn hash_file(path: &Path) -> std::io::Result<u64> {
+    Ok(hash_data(&tokio::fs::read(path).await?))
+}
+
+/// moves `file` to `dst`, replacing it if it exists.
+/// When renaming isn't possible, `file` is copied and only deleted after the copy was verified
+async fn move_verified(file: &Path, dst: &Path) -> Result<(), Error> {
+    let io_err = |source| Error::IO {
+        file: file.to_owned(),
+        dst: dst.to_owned(),
+        source,
+    };
+    if let Some(folder) = dst.parent() {
+        tokio::fs::create_dir_all(folder).await.map_err(io_err)?;
+    }
+    if tokio::fs::rename(file, dst).await.is_ok() {
+        return Ok(());
+    }
+    log::debug!("can't rename {file:?}, copying it to {dst:?}");
+    let mut tmp = dst.as_os_str().to_owned();
+    tmp.push(".part");
+    let tmp = PathBuf::from(tmp);
+    tokio::fs::copy(file, &tmp).await.map_err(io_err)?;
+    if hash_file(file).await.map_err(io_err)? != hash_file(&tmp).await.map_err(io_err)? {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(Error::Verify {
+            file: file.to_owned(),
+            dst: dst.to_owned(),
+        });
+    }
+    tokio::fs::rename(&tmp, dst).await.map_err(io_err)?;
+    tokio::fs::remove_file(file).await.map_err(io_err)
+}
+
+/// moves `file` to `dst`, comparing it with an existing file according to `collision`
+pub async fn move_file(
+    file: &Path,
+    dst: &Path,
+    collision: Collision,
+    dry_run: bool,
+) -> Result<Outcome, Error> {
+    let decision = if dst.exists() {
+        decide(
+            AudioInfo::read(file).await,
+            AudioInfo::read(dst).await,
+            collision,
+        )
+    } else {
+        Decision::Move
+    };
+    let outcome = match decision {
+        Decision::Move => Outcome::Moved(dst.to_owned()),
+        Decision::Suffix => Outcome::Suffixed(with_free_suffix(dst)),
+        Decision::Replace => Outcome::Replaced(dst.to_owned()),
+        Decision::Duplicate => Outcome::Duplicate(dst.to_owned()),
+        Decision::Keep => Outcome::Kept(dst.to_owned()),
+    };
+    if dry_run {
+        log::info!("{file:?} would be {outcome}");
+        return Ok(Outcome::DryRun(Box::new(outcome)));
+    }
+    match &outcome {
+        Outcome::Moved(dst) | Outcome::Suffixed(dst) | Outcome::Replaced(dst) => {
+            move_verified(file, dst).await?;
+        }
+        Outcome::Duplicate(_) => {
+            tokio::fs::remove_file(file)
+                .await
+                .map_err(|source| Error::IO {
+                    file: file.to_owned(),
+                    dst: dst.to_owned(),
+                    source,
+                })?;
+        }
+        Outcome::Kept(_) | Outcome::DryRun(_) => {}
+    }
+    Ok(outcome)
+}
+
+/// prints what happened to each file
+pub fn print_summary<'a>(
+    results: impl IntoIterator<Item = (&'a Path, &'a Result<Outcome, Error>)>,
+) {
+    for (file, result) in results {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        match result {
+            Ok(outcome) => println!("{name}: {outcome}"),
+            Err(err) => println!("{name}: failed, {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(secs: u64, bitrate: u32, hash: u64) -> AudioInfo {
+        AudioInfo {
+            quality: Some(Quality {
+                duration: Duration::from_secs(secs),
+                bitrate: f64::from(bitrate),
+            }),
+            hash,
+        }
+    }
+
+    #[test]
+    fn decide_keeps_better_version() {
+        let best = Collision::Best;
+        assert_eq!(
+            Decision::Duplicate,
+            decide(Some(info(600, 128, 1)), Some(info(600, 128, 1)), best)
+        );
+        assert_eq!(
+            Decision::Replace,
+            decide(Some(info(602, 192, 1)), Some(info(600, 128, 2)), best)
+        );
+        assert_eq!(
+            Decision::Keep,
+            decide(Some(info(600, 128, 1)), Some(info(601, 192, 2)), best)
+        );
+        assert_eq!(
+            Decision::Suffix,
+            decide(Some(info(300, 192, 1)), Some(info(600, 128, 2)), best)
+        );
+        assert_eq!(
+            Decision::Suffix,
+            decide(None, Some(info(600, 128, 2)), best)
+        );
+        assert_eq!(
+            Decision::Keep,
+            decide(
+                Some(info(300, 192, 1)),
+                Some(info(600, 128, 2)),
+                Collision::Skip
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn move_keeps_both_unknown_files() {
+        let dir = crate::worker::test_util::test_dir("mover-suffix");
+        let file = dir.join("work/chapter.mp3");
+        let dst = dir.join("out/chapter.mp3");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(dst.parent().unwrap()).unwrap();
+        std::fs::write(&file, b"new").unwrap();
+        std::fs::write(&dst, b"old").unwrap();
+        let suffixed = dir.join("out/chapter (1).mp3");
+
+        let planned = move_file(&file, &dst, Collision::Best, true).await.unwrap();
+        assert_eq!(
+            Outcome::DryRun(Box::new(Outcome::Suffixed(suffixed.clone()))),
+            planned
+        );
+        assert!(file.exists(), "moved in dry run");
+
+        let outcome = move_file(&file, &dst, Collision::Best, false)
+            .await
+            .unwrap();
+        assert_eq!(Outcome::Suffixed(suffixed.clone()), outcome);
+        assert!(!file.exists(), "source wasn't removed");
+        assert_eq!(b"new".as_slice(), std::fs::read(suffixed).unwrap());
+        assert_eq!(b"old".as_slice(), std::fs::read(dst).unwrap());
+    }
+
+    #[tokio::test]
+    async fn move_finds_same_opus() {
+        let dir = crate::worker::test_util::test_dir("mover-opus");
+        let file = dir.join("work/chapter.opus");
+        let dst = dir.join("out/chapter.opus");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(dst.parent().unwrap()).unwrap();
+        std::fs::write(&file, b"same").unwrap();
+        std::fs::write(&dst, b"same").unwrap();
+
+        let outcome = move_file(&file, &dst, Collision::Best, false)
+            .await
+            .unwrap();
+        assert_eq!(Outcome::Duplicate(dst.clone()), outcome);
+        assert!(!file.exists(), "duplicate wasn't removed");
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::archive::data::ChapterNumber;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("couldn't read plan {0:?}, because {1}")]
+    IO(PathBuf, #[source] std::io::Error),
+    #[error("invalid plan {0:?}, because {1}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+}
+
+/// the name of one label, known before the recording is named
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    /// the index of the label in the recording
+    pub index: usize,
+    pub series: String,
+    #[serde(deserialize_with = "deserialize_chapter")]
+    pub chapter: ChapterNumber,
+    /// the part of the chapter, counts up from the last label when missing
+    pub part: Option<usize>,
+    /// the title of the chapter, read from the index when missing
+    pub title: Option<String>,
+}
+
+fn deserialize_chapter<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ChapterNumber, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NrOrStr {
+        Nr(usize),
+        Str(String),
+    }
+    match NrOrStr::deserialize(deserializer)? {
+        NrOrStr::Nr(nr) => Ok(ChapterNumber::from(nr)),
+        NrOrStr::Str(s) => s
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid chapter number {s:?}"))),
+    }
+}
+
+/// maps label indices to the names they should get
+///
+/// # Example
+/// ```toml
+/// [[label]]
+/// index = 0
+/// series = "Gruselkabinett"
+/// chapter = 6
+///
+/// [[label]]
+/// index = 1
+/// series = "Gruselkabinett"
+/// chapter = "6"
+/// part = 2
+/// title = "Das verfluchte Haus"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Plan {
+    #[serde(default, rename = "label")]
+    entries: Vec<Entry>,
+}
+
+impl Plan {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content =
+            std::fs::read_to_string(path).map_err(|err| Error::IO(path.to_path_buf(), err))?;
+        Self::from_toml_str(&content).map_err(|err| Error::Parse(path.to_path_buf(), err))
+    }
+    pub fn from_toml_str(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// the entry for the `i`-th label
+    #[must_use]
+    pub fn get(&self, i: usize) -> Option<&Entry> {
+        self.entries.iter().rfind(|entry| entry.index == i)
+    }
+    /// removes the entry for the `i`-th label, so it is only used once
+    pub fn remove(&mut self, i: usize) -> Option<Entry> {
+        let pos = self.entries.iter().rposition(|entry| entry.index == i)?;
+        Some(self.entries.remove(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = r#"
+        [[label]]
+        index = 0
+        series = "Gruselkabinett"
+        chapter = 6
+
+        [[label]]
+        index = 2
+        series = "Gruselkabinett"
+        chapter = "7"
+        part = 2
+        title = "Der Turm"
+    "#;
+
+    #[test]
+    fn read_entries() {
+        let plan = Plan::from_toml_str(PLAN).unwrap();
+        assert_eq!(
+            Some(&Entry {
+                index: 0,
+                series: "Gruselkabinett".to_owned(),
+                chapter: ChapterNumber::from(6),
+                part: None,
+                title: None,
+            }),
+            plan.get(0)
+        );
+        assert_eq!(None, plan.get(1));
+        let entry = plan.get(2).unwrap();
+        assert_eq!(ChapterNumber::from(7), entry.chapter);
+        assert_eq!(Some(2), entry.part);
+        assert_eq!(Some("Der Turm"), entry.title.as_deref());
+    }
+
+    #[test]
+    fn invalid_chapter() {
+        let err = Plan::from_toml_str("[[label]]\nindex = 0\nseries = \"a\"\nchapter = \"b\"")
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid chapter number"));
+    }
+}
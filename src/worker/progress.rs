@@ -0,0 +1,618 @@
+//! remembers how far each recording got, so an interrupted run can be resumed
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::fs;
+use toml::value::Datetime;
+
+use crate::splitter::recording::Recording;
+
+pub const FILE_NAME: &str = ".progress.toml";
+/// the progress file of older versions with lines like `name.mp3 done`
+pub const OLD_FILE_NAME: &str = ".done.txt";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("couldn't access {0:?}, because {1}")]
+    IO(PathBuf, #[source] std::io::Error),
+    #[error("invalid progress file {0:?}, because {1}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+    #[error("couldn't encode progress, because {0}")]
+    Encode(#[from] toml::ser::Error),
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Loaded,
+    Named,
+    /// the parts of each chapter were merged in the project
+    Merged,
+    /// each chapter was exported into its own file
+    Exported,
+    Tagged,
+    /// the tagged files were moved to their destination
+    #[serde(alias = "done")]
+    Moved,
+}
+impl Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Loaded => "loaded",
+            Self::Named => "named",
+            Self::Merged => "merged",
+            Self::Exported => "exported",
+            Self::Tagged => "tagged",
+            Self::Moved => "moved",
+        })
+    }
+}
+
+/// a cheap identification of the audio file, hashing whole recordings would take too long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioStamp {
+    pub len: u64,
+    /// seconds since the unix epoch
+    pub modified: Option<u64>,
+}
+impl AudioStamp {
+    async fn read(path: &Path) -> Result<Self, Error> {
+        let meta = fs::metadata(path)
+            .await
+            .map_err(|err| Error::IO(path.to_owned(), err))?;
+        Ok(Self {
+            len: meta.len(),
+            modified: meta
+                .modified()
+                .ok()
+                .and_then(|it| it.duration_since(UNIX_EPOCH).ok())
+                .map(|it| it.as_secs()),
+        })
+    }
+}
+
+/// hashes the content of `path` with FNV-1a, which is stable between runs and versions
+async fn hash_file(path: &Path) -> Result<Option<String>, Error> {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    match fs::read(path).await {
+        Ok(data) => Ok(Some(format!(
+            "{:016x}",
+            data.iter()
+                .fold(OFFSET, |hash, &b| (hash ^ u64::from(b)).wrapping_mul(PRIME))
+        ))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::IO(path.to_owned(), err)),
+    }
+}
+
+/// the states of [`OLD_FILE_NAME`], which knew only loaded, named and done
+fn parse_old_state(state: &str) -> Option<State> {
+    match state.to_ascii_lowercase().as_str() {
+        "loaded" => Some(State::Loaded),
+        "named" => Some(State::Named),
+        "done" => Some(State::Moved),
+        _ => None,
+    }
+}
+
+fn now() -> Datetime {
+    chrono::Local::now()
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+        .parse()
+        .expect("rfc3339 is valid toml")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reached {
+    pub state: State,
+    pub at: Datetime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub state: State,
+    /// the hash of the label file, when the last state was reached
+    pub labels: Option<String>,
+    /// the last error, cleared when the next state is reached
+    pub error: Option<String>,
+    #[serde(default)]
+    pub outputs: Vec<PathBuf>,
+    pub audio: Option<AudioStamp>,
+    /// the files, that were joined into the audio of a split recording
+    #[serde(default)]
+    pub parts: Vec<PathBuf>,
+    #[serde(default)]
+    pub reached: Vec<Reached>,
+}
+impl Record {
+    const fn new(state: State) -> Self {
+        Self {
+            state,
+            labels: None,
+            error: None,
+            outputs: Vec::new(),
+            audio: None,
+            parts: Vec::new(),
+            reached: Vec::new(),
+        }
+    }
+    #[must_use]
+    pub fn last_change(&self) -> Option<Datetime> {
+        self.reached.last().map(|it| it.at)
+    }
+}
+
+/// what changed since a [`State`] was reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Audio,
+    Labels,
+}
+
+/// the progress of all recordings in one folder
+///
+/// # Example
+/// ```toml
+/// [files."/recordings/2023-04-01 Gruselkabinett.mp3"]
+/// state = "named"
+/// labels = "af63bd4c8601b7be"
+/// outputs = []
+///
+/// [files."/recordings/2023-04-01 Gruselkabinett.mp3".audio]
+/// len = 123456789
+/// modified = 1680300000
+///
+/// [[files."/recordings/2023-04-01 Gruselkabinett.mp3".reached]]
+/// state = "loaded"
+/// at = 2023-04-02T10:00:00+02:00
+/// ```
+#[derive(Debug)]
+pub struct Progress {
+    file: PathBuf,
+    files: BTreeMap<String, Record>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Content {
+    #[serde(default)]
+    files: BTreeMap<String, Record>,
+}
+
+/// the key of `audio_path`, absolute if possible so the same file is found from everywhere.
+/// Only the folder is resolved, so deleted files keep their key
+#[must_use]
+pub fn key(audio_path: &Path) -> String {
+    let dir = audio_path
+        .parent()
+        .filter(|it| !it.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    std::fs::canonicalize(dir)
+        .ok()
+        .zip(audio_path.file_name())
+        .map_or_else(|| audio_path.to_owned(), |(dir, name)| dir.join(name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+impl Progress {
+    /// reads the progress from `path`, a missing file means nothing was done yet
+    pub async fn read(path: impl Into<PathBuf> + Send) -> Result<Self, Error> {
+        let file = path.into();
+        let content = match fs::read_to_string(&file).await {
+            Ok(data) => {
+                toml::from_str::<Content>(&data).map_err(|err| Error::Parse(file.clone(), err))?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Content::default(),
+            Err(err) => return Err(Error::IO(file, err)),
+        };
+        Ok(Self {
+            file,
+            files: content.files,
+        })
+    }
+    /// reads the progress stored in the folder `dir`.
+    /// Without a progress file, the [`OLD_FILE_NAME`] of older versions is imported once
+    pub async fn read_in(dir: &Path) -> Result<Self, Error> {
+        let file = dir.join(FILE_NAME);
+        let old = dir.join(OLD_FILE_NAME);
+        if file.exists() || !old.exists() {
+            return Self::read(file).await;
+        }
+        let data = fs::read_to_string(&old)
+            .await
+            .map_err(|err| Error::IO(old.clone(), err))?;
+        let mut progress = Self {
+            file,
+            files: BTreeMap::new(),
+        };
+        for (i, line) in data
+            .lines()
+            .enumerate()
+            .filter(|(_, it)| !it.trim().is_empty())
+        {
+            match line
+                .rsplit_once(' ')
+                .and_then(|(name, state)| Some((name, parse_old_state(state)?)))
+            {
+                Some((name, state)) => {
+                    progress
+                        .files
+                        .insert(key(&dir.join(name)), Record::new(state));
+                }
+                None => log::warn!("can't import line {i} {line:?} of {old:?}, ignoring it"),
+            }
+        }
+        log::info!("imported {} recordings from {old:?}", progress.files.len());
+        progress.save().await?;
+        Ok(progress)
+    }
+
+    /// writes to a temporary file first, so an interrupted save doesn't lose the old progress
+    pub async fn save(&self) -> Result<(), Error> {
+        let data = toml::to_string(&Content {
+            files: self.files.clone(),
+        })?;
+        let tmp = self.file.with_extension("toml.tmp");
+        fs::write(&tmp, data)
+            .await
+            .map_err(|err| Error::IO(tmp.clone(), err))?;
+        fs::rename(&tmp, &self.file)
+            .await
+            .map_err(|err| Error::IO(self.file.clone(), err))
+    }
+
+    /// the key of the record of `audio_path`.
+    /// A split recording is also found by one of its parts or by its name without part number
+    fn resolve(&self, audio_path: &Path) -> String {
+        let wanted = key(audio_path);
+        if self.files.contains_key(&wanted) {
+            return wanted;
+        }
+        self.files
+            .iter()
+            .find(|(_, record)| {
+                record.parts.iter().any(|part| key(part) == wanted)
+                    || Recording::group(record.parts.iter().cloned())
+                        .first()
+                        .is_some_and(|it| key(it.path()) == wanted)
+            })
+            .map_or(wanted, |(key, _)| key.clone())
+    }
+
+    #[must_use]
+    pub fn get(&self, audio_path: &Path) -> Option<State> {
+        self.record(audio_path).map(|it| it.state)
+    }
+    #[must_use]
+    pub fn record(&self, audio_path: &Path) -> Option<&Record> {
+        self.files.get(&self.resolve(audio_path))
+    }
+    pub fn records(&self) -> impl Iterator<Item = (&str, &Record)> {
+        self.files
+            .iter()
+            .map(|(key, record)| (key.as_str(), record))
+    }
+    pub fn remove(&mut self, audio_path: &Path) -> Option<Record> {
+        self.files.remove(&self.resolve(audio_path))
+    }
+    /// goes back to `state`, forgetting everything reached after it.
+    /// Returns the old [`State`], if `audio_path` is known
+    pub fn set(&mut self, audio_path: &Path, state: State) -> Option<State> {
+        let record = self.files.get_mut(&self.resolve(audio_path))?;
+        let old = record.state;
+        record.state = state;
+        record.reached.retain(|it| it.state <= state);
+        record.error = None;
+        if state < State::Moved {
+            record.outputs.clear();
+        }
+        Some(old)
+    }
+    /// forgets all recordings, that no longer exist and returns their keys.
+    /// A split recording exists as long as one of its parts exists
+    pub fn clean(&mut self) -> Vec<String> {
+        let (gone, kept) = std::mem::take(&mut self.files)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(key, record)| {
+                !Path::new(key).exists() && !record.parts.iter().any(|it| it.exists())
+            });
+        self.files = kept.into_iter().collect();
+        gone.into_iter().map(|(key, _)| key).collect()
+    }
+
+    /// checks if the audio or label file changed since the last [`State`] of `audio_path` was reached
+    pub async fn changed(
+        &self,
+        audio_path: &Path,
+        label_path: &Path,
+    ) -> Result<Option<Change>, Error> {
+        let Some(record) = self.record(audio_path) else {
+            return Ok(None);
+        };
+        if record.audio.is_some() && record.audio != AudioStamp::read(audio_path).await.ok() {
+            return Ok(Some(Change::Audio));
+        }
+        if record.labels.is_some() && record.labels != hash_file(label_path).await? {
+            return Ok(Some(Change::Labels));
+        }
+        Ok(None)
+    }
+
+    /// marks that `audio_path` reached `state` and remembers its files to detect later changes
+    pub async fn reach(
+        &mut self,
+        audio_path: &Path,
+        label_path: &Path,
+        state: State,
+    ) -> Result<(), Error> {
+        let audio = AudioStamp::read(audio_path).await?;
+        let labels = hash_file(label_path).await?;
+        let record = self
+            .files
+            .entry(key(audio_path))
+            .or_insert_with(|| Record::new(state));
+        record.state = state;
+        record.labels = labels;
+        record.audio = Some(audio);
+        record.error = None;
+        record.reached.push(Reached { state, at: now() });
+        self.save().await
+    }
+    /// remembers the files produced for `audio_path`
+    pub async fn set_outputs(
+        &mut self,
+        audio_path: &Path,
+        outputs: Vec<PathBuf>,
+    ) -> Result<(), Error> {
+        if let Some(record) = self.files.get_mut(&key(audio_path)) {
+            record.outputs = outputs;
+        }
+        self.save().await
+    }
+    /// remembers one more file produced for `audio_path`, so it is known even after an interruption
+    pub async fn add_output(&mut self, audio_path: &Path, output: PathBuf) -> Result<(), Error> {
+        if let Some(record) = self.files.get_mut(&key(audio_path)) {
+            record.outputs.push(output);
+        }
+        self.save().await
+    }
+    /// remembers the files, that were joined into `audio_path`, so it can be found by them
+    pub async fn set_parts(&mut self, audio_path: &Path, parts: &[PathBuf]) -> Result<(), Error> {
+        if let Some(record) = self.files.get_mut(&key(audio_path)) {
+            record.parts = parts.iter().map(|it| PathBuf::from(key(it))).collect();
+        }
+        self.save().await
+    }
+    /// remembers that working on `audio_path` failed, doesn't change its [`State`]
+    pub async fn fail(
+        &mut self,
+        audio_path: &Path,
+        error: impl Display + Send,
+    ) -> Result<(), Error> {
+        if let Some(record) = self.files.get_mut(&key(audio_path)) {
+            record.error = Some(error.to_string());
+            self.save().await?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.files.is_empty() {
+            return writeln!(f, "nothing done yet");
+        }
+        writeln!(f, "{:<8} {:<25} file", "state", "last change")?;
+        for (key, record) in &self.files {
+            let name = Path::new(key)
+                .file_name()
+                .map_or_else(|| key.clone(), |it| it.to_string_lossy().into_owned());
+            writeln!(
+                f,
+                "{:<8} {:<25} {name}",
+                record.state.to_string(),
+                record
+                    .last_change()
+                    .map_or_else(String::new, |it| it.to_string())
+            )?;
+            for output in &record.outputs {
+                writeln!(f, "    -> {output:?}")?;
+            }
+            if let Some(error) = &record.error {
+                writeln!(f, "    failed: {error}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::test_util::test_dir;
+
+    #[tokio::test]
+    async fn reach_survives_reload() {
+        let dir = test_dir("progress-reload");
+        let audio = dir.join("audio.mp3");
+        let labels = dir.join("audio.txt");
+        std::fs::write(&audio, b"audio").unwrap();
+        std::fs::write(&labels, b"0\t1\tlabel").unwrap();
+
+        let mut progress = Progress::read_in(&dir).await.unwrap();
+        assert_eq!(None, progress.get(&audio));
+        progress
+            .reach(&audio, &labels, State::Loaded)
+            .await
+            .unwrap();
+        progress.reach(&audio, &labels, State::Named).await.unwrap();
+        progress
+            .set_outputs(&audio, vec![dir.join("out.mp3")])
+            .await
+            .unwrap();
+
+        let progress = Progress::read_in(&dir).await.unwrap();
+        let record = progress.record(&audio).unwrap();
+        assert_eq!(State::Named, record.state);
+        assert_eq!(
+            vec![State::Loaded, State::Named],
+            record.reached.iter().map(|it| it.state).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![dir.join("out.mp3")], record.outputs);
+        assert_eq!(None, progress.changed(&audio, &labels).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn detects_changes() {
+        let dir = test_dir("progress-changes");
+        let audio = dir.join("audio.mp3");
+        let labels = dir.join("audio.txt");
+        std::fs::write(&audio, b"audio").unwrap();
+        std::fs::write(&labels, b"0\t1\tlabel").unwrap();
+
+        let mut progress = Progress::read_in(&dir).await.unwrap();
+        progress.reach(&audio, &labels, State::Named).await.unwrap();
+
+        std::fs::write(&labels, b"0\t1\tother").unwrap();
+        assert_eq!(
+            Some(Change::Labels),
+            progress.changed(&audio, &labels).await.unwrap()
+        );
+        std::fs::write(&audio, b"longer audio").unwrap();
+        assert_eq!(
+            Some(Change::Audio),
+            progress.changed(&audio, &labels).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn set_forgets_later_states() {
+        let dir = test_dir("progress-set");
+        let audio = dir.join("audio.mp3");
+        let labels = dir.join("audio.txt");
+        std::fs::write(&audio, b"audio").unwrap();
+
+        let mut progress = Progress::read_in(&dir).await.unwrap();
+        for state in [State::Loaded, State::Named, State::Merged, State::Moved] {
+            progress.reach(&audio, &labels, state).await.unwrap();
+        }
+        progress
+            .set_outputs(&audio, vec![dir.join("out.mp3")])
+            .await
+            .unwrap();
+
+        assert_eq!(Some(State::Moved), progress.set(&audio, State::Named));
+        let record = progress.record(&audio).unwrap();
+        assert_eq!(State::Named, record.state);
+        assert_eq!(
+            vec![State::Loaded, State::Named],
+            record.reached.iter().map(|it| it.state).collect::<Vec<_>>()
+        );
+        assert!(record.outputs.is_empty(), "outputs weren't forgotten");
+        assert_eq!(None, progress.set(&dir.join("other.mp3"), State::Named));
+    }
+
+    #[tokio::test]
+    async fn finds_split_recording_by_parts() {
+        let dir = test_dir("progress-parts");
+        let joined = dir.join("rec (joined).mp3");
+        let labels = dir.join("rec.txt");
+        std::fs::write(&joined, b"audio").unwrap();
+
+        let mut progress = Progress::read_in(&dir).await.unwrap();
+        progress
+            .reach(&joined, &labels, State::Loaded)
+            .await
+            .unwrap();
+        progress
+            .set_parts(&joined, &[dir.join("rec (1).mp3"), dir.join("rec (2).mp3")])
+            .await
+            .unwrap();
+
+        for path in ["rec (2).mp3", "rec.mp3"] {
+            assert_eq!(
+                Some(State::Loaded),
+                progress.get(&dir.join(path)),
+                "not found by {path}"
+            );
+        }
+        assert_eq!(None, progress.get(&dir.join("rec (3).mp3")));
+        assert_eq!(
+            Some(State::Loaded),
+            progress.set(&dir.join("rec (1).mp3"), State::Loaded)
+        );
+    }
+
+    #[tokio::test]
+    async fn clean_forgets_missing() {
+        let dir = test_dir("progress-clean");
+        let kept = dir.join("kept.mp3");
+        let gone = dir.join("gone.mp3");
+        let labels = dir.join("audio.txt");
+        std::fs::write(&kept, b"audio").unwrap();
+        std::fs::write(&gone, b"audio").unwrap();
+
+        let mut progress = Progress::read_in(&dir).await.unwrap();
+        progress.reach(&kept, &labels, State::Loaded).await.unwrap();
+        progress.reach(&gone, &labels, State::Loaded).await.unwrap();
+        let gone_key = key(&gone);
+        std::fs::remove_file(&gone).unwrap();
+
+        assert_eq!(vec![gone_key], progress.clean());
+        assert_eq!(Some(State::Loaded), progress.get(&kept));
+    }
+
+    #[tokio::test]
+    async fn imports_old_progress() {
+        let dir = test_dir("progress-import");
+        std::fs::copy("res/progress.txt", dir.join(OLD_FILE_NAME)).unwrap();
+
+        let progress = Progress::read_in(&dir).await.unwrap();
+        assert_eq!(
+            vec![
+                Some(State::Moved),
+                Some(State::Loaded),
+                Some(State::Moved),
+                Some(State::Named),
+                None
+            ],
+            (1..=5)
+                .map(|i| progress.get(&dir.join(format!("element {i}"))))
+                .collect::<Vec<_>>()
+        );
+        assert!(dir.join(FILE_NAME).exists(), "import wasn't saved");
+
+        std::fs::write(dir.join(OLD_FILE_NAME), b"element 5 done").unwrap();
+        let progress = Progress::read_in(&dir).await.unwrap();
+        assert_eq!(None, progress.get(&dir.join("element 5")), "imported twice");
+    }
+
+    #[tokio::test]
+    async fn fail_keeps_state() {
+        let dir = test_dir("progress-fail");
+        let audio = dir.join("audio.mp3");
+        let labels = dir.join("audio.txt");
+        std::fs::write(&audio, b"audio").unwrap();
+
+        let mut progress = Progress::read_in(&dir).await.unwrap();
+        progress
+            .reach(&audio, &labels, State::Loaded)
+            .await
+            .unwrap();
+        progress.fail(&audio, "no project loaded").await.unwrap();
+
+        let record = progress.record(&audio).unwrap();
+        assert_eq!(State::Loaded, record.state);
+        assert_eq!(Some("no project loaded"), record.error.as_deref());
+        assert_eq!(None, record.labels, "label file doesn't exist");
+    }
+}
@@ -0,0 +1,301 @@
+//! the path a finished chapter is moved to, built from a template like
+//! `{artist}/{series}/{nr:03} - {title}.{ext}`
+use std::{path::PathBuf, str::FromStr};
+
+use thiserror::Error;
+
+use crate::archive::data::ChapterNumber;
+
+use super::index::MultiIndex;
+
+/// the layout of the archive, a folder for each series and subseries
+pub const DEFAULT: &str = "{main_series}/{subseries}/{series} {nr} {title}.{ext}";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("unknown field {0:?} in output template")]
+    UnknownField(String),
+    #[error("invalid format {1:?} for {0:?} in output template, only a width like '03' is allowed for nr and year")]
+    Format(String, String),
+    #[error("unclosed '{{' in output template")]
+    Unclosed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Series,
+    MainSeries,
+    Subseries,
+    Nr,
+    Title,
+    Artist,
+    Year,
+    Ext,
+}
+impl FromStr for Field {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "series" => Self::Series,
+            "main_series" => Self::MainSeries,
+            "subseries" => Self::Subseries,
+            "nr" => Self::Nr,
+            "title" => Self::Title,
+            "artist" => Self::Artist,
+            "year" => Self::Year,
+            "ext" => Self::Ext,
+            _ => return Err(Error::UnknownField(s.to_owned())),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    /// a field with an optional width, padded with zeros when the bool is set
+    Field(Field, Option<(usize, bool)>),
+}
+
+/// the values of one chapter, that can be used in a [`Template`]
+#[derive(Debug, Clone, Copy)]
+pub struct Values<'a> {
+    pub series: &'a str,
+    pub nr: ChapterNumber,
+    pub title: &'a str,
+    pub artist: Option<&'a str>,
+    pub year: Option<i32>,
+    pub ext: &'a str,
+}
+impl Values<'_> {
+    fn get(&self, field: Field, width: Option<(usize, bool)>) -> String {
+        let (main, sub) = self
+            .series
+            .split_once(MultiIndex::SUBSERIES_DELIMENITER)
+            .map_or((self.series, None), |(main, sub)| (main, Some(sub)));
+        match field {
+            Field::Series => self.series.to_owned(),
+            Field::MainSeries => main.to_owned(),
+            Field::Subseries => sub.unwrap_or_default().to_owned(),
+            Field::Nr => self.nr.as_display(width, false).to_string(),
+            Field::Title => self.title.to_owned(),
+            Field::Artist => self.artist.unwrap_or_default().to_owned(),
+            Field::Year => self.year.map_or_else(String::new, |year| match width {
+                Some((width, true)) => format!("{year:0width$}"),
+                Some((width, false)) => format!("{year:width$}"),
+                None => year.to_string(),
+            }),
+            Field::Ext => self.ext.to_owned(),
+        }
+    }
+}
+
+/// a path with `{field}` placeholders, '/' separates folders.
+///
+/// Known fields are `series`, `main_series`, `subseries`, `nr`, `title`, `artist`, `year` and `ext`,
+/// `nr` and `year` accept a width like `{nr:03}`. Use `{{` and `}}` for literal braces.
+/// Folders, that are empty after filling in the fields, are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Vec<Piece>>,
+}
+impl Default for Template {
+    fn default() -> Self {
+        DEFAULT.parse().expect("default template is valid")
+    }
+}
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = vec![Vec::new()];
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut is_closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            is_closed = true;
+                            break;
+                        }
+                        placeholder.push(c);
+                    }
+                    if !is_closed {
+                        return Err(Error::Unclosed);
+                    }
+                    let (name, format) = placeholder
+                        .split_once(':')
+                        .map_or((placeholder.as_str(), None), |(name, format)| {
+                            (name, Some(format))
+                        });
+                    let field = name.trim().parse::<Field>()?;
+                    let width = format
+                        .map(|format| match (field, format.parse::<usize>()) {
+                            (Field::Nr | Field::Year, Ok(width)) => {
+                                Ok((width, format.starts_with('0')))
+                            }
+                            _ => Err(Error::Format(name.to_owned(), format.to_owned())),
+                        })
+                        .transpose()?;
+                    let segment = segments.last_mut().expect("has a segment");
+                    if !text.is_empty() {
+                        segment.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    segment.push(Piece::Field(field, width));
+                }
+                '/' => {
+                    if !text.is_empty() {
+                        let segment = segments.last_mut().expect("has a segment");
+                        segment.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Vec::new());
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            let segment = segments.last_mut().expect("has a segment");
+            segment.push(Piece::Text(text));
+        }
+        Ok(Self { segments })
+    }
+}
+impl Template {
+    /// fills in `values`, each value is sanitized, so it can't add folders or invalid characters
+    #[must_use]
+    pub fn render(&self, values: &Values<'_>) -> PathBuf {
+        self.segments
+            .iter()
+            .map(|segment| {
+                let rendered = segment
+                    .iter()
+                    .map(|piece| match piece {
+                        Piece::Text(text) => text.clone(),
+                        Piece::Field(field, width) => sanitize(&values.get(*field, *width)),
+                    })
+                    .collect::<String>();
+                trim_segment(&rendered)
+            })
+            .filter(|segment| !segment.is_empty())
+            .collect()
+    }
+}
+
+/// replaces the characters, that are invalid on FAT and SMB shares
+#[must_use]
+pub fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// removes spaces around and dots after a file name and escapes names reserved by windows
+fn trim_segment(segment: &str) -> String {
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let segment = segment.trim().trim_end_matches('.').trim_end();
+    let stem = segment.split('.').next().unwrap_or_default();
+    if RESERVED.iter().any(|it| it.eq_ignore_ascii_case(stem)) {
+        format!("_{segment}")
+    } else {
+        segment.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Values<'static> {
+        Values {
+            series: "Gruselkabinett",
+            nr: ChapterNumber::from(6),
+            title: "Das Haus: Teil 1?",
+            artist: None,
+            year: Some(2004),
+            ext: "mp3",
+        }
+    }
+
+    #[test]
+    fn default_layout() {
+        assert_eq!(
+            PathBuf::from("Gruselkabinett/Gruselkabinett 6 Das Haus_ Teil 1_.mp3"),
+            Template::default().render(&values())
+        );
+        let values = Values {
+            series: "Sherlock Holmes: Die neuen F\u{e4}lle",
+            ..values()
+        };
+        assert_eq!(
+            PathBuf::from(
+                "Sherlock Holmes/Die neuen F\u{e4}lle/Sherlock Holmes_ Die neuen F\u{e4}lle 6 Das Haus_ Teil 1_.mp3"
+            ),
+            Template::default().render(&values)
+        );
+    }
+
+    #[test]
+    fn custom_layout() {
+        let template = "{artist}/{series}/{nr:03} - {title} ({year}).{ext}"
+            .parse::<Template>()
+            .unwrap();
+        assert_eq!(
+            PathBuf::from("Gruselkabinett/006 - Das Haus_ Teil 1_ (2004).mp3"),
+            template.render(&values())
+        );
+        let values = Values {
+            artist: Some("Titania Medien"),
+            ..values()
+        };
+        assert_eq!(
+            PathBuf::from("Titania Medien/Gruselkabinett/006 - Das Haus_ Teil 1_ (2004).mp3"),
+            template.render(&values)
+        );
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert_eq!(
+            Err(Error::UnknownField("name".to_owned())),
+            "{name}.mp3".parse::<Template>()
+        );
+        assert_eq!(
+            Err(Error::Format("title".to_owned(), "03".to_owned())),
+            "{title:03}".parse::<Template>()
+        );
+        assert_eq!(Err(Error::Unclosed), "{title".parse::<Template>());
+        assert_eq!(
+            PathBuf::from("{Gruselkabinett}"),
+            "{{{series}}}"
+                .parse::<Template>()
+                .unwrap()
+                .render(&values())
+        );
+    }
+
+    #[test]
+    fn reserved_names() {
+        assert_eq!("_con.mp3", trim_segment("con.mp3"));
+        assert_eq!("Konsole", trim_segment(" Konsole. "));
+    }
+}
@@ -0,0 +1,34 @@
+//! fixtures shared by the tests of the worker
+use std::path::{Path, PathBuf};
+
+use audacity::data::TimeLabel;
+
+use super::backend::{Backend, Files};
+
+/// a clean folder for the files of the test `name`
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("audio-matcher-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("couldn't create test dir");
+    dir
+}
+
+/// writes `labels` into the label file `path`
+pub fn write_labels(labels: impl IntoIterator<Item = TimeLabel>, path: &Path) {
+    assert!(
+        TimeLabel::write(labels, path, false).is_ok(),
+        "couldn't write labels"
+    );
+}
+
+/// a [`Files`] backend, that loaded `labels` from a label file in `dir`
+pub async fn files_backend(dir: &Path, labels: impl IntoIterator<Item = TimeLabel>) -> Files {
+    let label_path = dir.join("labels.txt");
+    write_labels(labels, &label_path);
+    let mut backend = Files::default();
+    backend
+        .load(Path::new("recording.mp3"), &label_path)
+        .await
+        .unwrap();
+    backend
+}